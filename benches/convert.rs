@@ -0,0 +1,55 @@
+//! Times the sample conversion `push` runs on every frame, simd128 path against the scalar one.
+//!
+//! Run natively with `cargo bench --bench convert`, where both sides are the scalar code. To
+//! measure the simd128 path, run it as a wasm binary:
+//!
+//! ```text
+//! RUSTFLAGS="-C target-feature=+simd128" CARGO_TARGET_WASM32_WASIP1_RUNNER=wasmtime \
+//!     cargo bench --bench convert --target wasm32-wasip1 --no-default-features
+//! ```
+
+use std::collections::VecDeque;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+#[path = "../src/convert.rs"]
+mod convert;
+
+type Extend = fn(&mut VecDeque<f32>, &[i32], u32, f32, &mut f32);
+
+fn measure(name: &str, extend: Extend, samples: &[i32], bits: u32) -> Duration {
+    let mut output = VecDeque::with_capacity(samples.len());
+    let mut best = Duration::MAX;
+    for _ in 0..10 {
+        output.clear();
+        let mut peak = 0.0;
+        let start = Instant::now();
+        extend(&mut output, black_box(samples), bits, 0.8, &mut peak);
+        best = best.min(start.elapsed());
+        black_box(peak);
+    }
+    println!(
+        "{:<8} {:>2} bits {:>10.2?}  ({:.2} ns/sample)",
+        name,
+        bits,
+        best,
+        best.as_nanos() as f64 / samples.len() as f64
+    );
+    best
+}
+
+fn main() {
+    let simd = cfg!(all(target_arch = "wasm32", target_feature = "simd128"));
+    println!("simd128 {}", if simd { "enabled" } else { "disabled: both paths are scalar" });
+
+    // One minute of 44.1 kHz audio per run.
+    let len = 44_100 * 60;
+    for &bits in &[16, 24] {
+        let samples: Vec<i32> = (0..len as i64)
+            .map(|i| ((i * 7919) % (1 << bits) - (1 << (bits - 1))) as i32)
+            .collect();
+        let scalar = measure("scalar", convert::extend_f32_scalar, &samples, bits);
+        let simd = measure("simd", convert::extend_f32, &samples, bits);
+        println!("speedup  {:.2}x", scalar.as_secs_f64() / simd.as_secs_f64());
+    }
+}
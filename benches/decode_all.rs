@@ -0,0 +1,64 @@
+//! Counts the allocations `Decoder::decode_all` makes with and without a known stream length.
+//!
+//! Run with `cargo bench --bench decode_all`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use flac_decoder::Decoder;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn measure(name: &str, data: &[u8]) {
+    let data = data.to_vec().into_boxed_slice();
+    let (allocations, bytes) = (ALLOCATIONS.load(Ordering::Relaxed), BYTES.load(Ordering::Relaxed));
+    let start = Instant::now();
+    let audio = Decoder::decode_all(data).unwrap();
+    let elapsed = start.elapsed();
+    println!(
+        "{:<16} {:>6} allocations {:>12} bytes {:>10.2?}  ({} frames)",
+        name,
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        BYTES.load(Ordering::Relaxed) - bytes,
+        elapsed,
+        audio.frames(),
+    );
+}
+
+fn main() {
+    // Ten minutes of stereo at 44.1 kHz.
+    let frames = 44_100 * 600;
+    let channels = vec![common::sine(frames, 440.0, 0.5, 16), common::sine(frames, 660.0, 0.5, 16)];
+    let flac = common::Flac::new(44_100, 16, channels).block_size(4096);
+
+    measure("known length", &flac.build());
+    measure("unknown length", &flac.total_samples(0).build());
+}
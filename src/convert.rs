@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+
+/// Maps a `bits`-wide sample onto `-1.0..1.0`, full-scale negative landing exactly on -1.0.
+pub fn sample_to_f32(sample: i32, bits: u32) -> f32 {
+    sample as f32 / (1u64 << (bits - 1)) as f32
+}
+
+/// Appends `samples` converted to floats and multiplied by `scale`, raising `peak` to the
+/// largest magnitude written.
+#[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+pub fn extend_f32(output: &mut VecDeque<f32>, samples: &[i32], bits: u32, scale: f32, peak: &mut f32) {
+    extend_f32_scalar(output, samples, bits, scale, peak);
+}
+
+/// The conversion one sample at a time, built on every target so the simd128 path can be
+/// checked against it.
+pub fn extend_f32_scalar(output: &mut VecDeque<f32>, samples: &[i32], bits: u32, scale: f32, peak: &mut f32) {
+    output.extend(samples.iter().map(|&s| {
+        let s = sample_to_f32(s, bits) * scale;
+        *peak = peak.max(s.abs());
+        s
+    }));
+}
+
+/// Like the scalar version, four samples at a time. Dividing by the power-of-two full scale is
+/// exact in both, so the results are bit-identical.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+pub fn extend_f32(output: &mut VecDeque<f32>, samples: &[i32], bits: u32, scale: f32, peak: &mut f32) {
+    use core::arch::wasm32::*;
+
+    let full_scale = f32x4_splat((1u64 << (bits - 1)) as f32);
+    let gain = f32x4_splat(scale);
+    let mut peaks = f32x4_splat(*peak);
+
+    output.reserve(samples.len());
+    let mut chunks = samples.chunks_exact(4);
+    for chunk in &mut chunks {
+        let ints = i32x4(chunk[0], chunk[1], chunk[2], chunk[3]);
+        let floats = f32x4_mul(f32x4_div(f32x4_convert_i32x4(ints), full_scale), gain);
+        peaks = f32x4_max(peaks, f32x4_abs(floats));
+        output.extend(&[
+            f32x4_extract_lane::<0>(floats),
+            f32x4_extract_lane::<1>(floats),
+            f32x4_extract_lane::<2>(floats),
+            f32x4_extract_lane::<3>(floats),
+        ]);
+    }
+
+    *peak = f32x4_extract_lane::<0>(peaks)
+        .max(f32x4_extract_lane::<1>(peaks))
+        .max(f32x4_extract_lane::<2>(peaks))
+        .max(f32x4_extract_lane::<3>(peaks));
+    extend_f32_scalar(output, chunks.remainder(), bits, scale, peak);
+}
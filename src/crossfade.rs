@@ -0,0 +1,34 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::Decoder;
+
+/// Pulls up to `frames` floats from both decoders and writes `a` fading out into `b` fading in
+/// to the output slices, and returns the number of frames written.
+///
+/// The gains follow the same square-root curve as `FadeCurve::EqualPower`, so the two always sum
+/// to constant power, √½ each at the midpoint. A decoder that runs out early plays silence for
+/// the rest; the count covers the longer of the two. Mono decoders play on both sides and only
+/// the first two channels of surround output are mixed. `frames` is limited by the length of
+/// the shorter output slice.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub fn crossfade(a: &mut Decoder, b: &mut Decoder, frames: usize, out_left: &mut [f32], out_right: &mut [f32]) -> usize {
+    let frames = frames.min(out_left.len()).min(out_right.len());
+    let produced = a.pull_f32(frames).max(b.pull_f32(frames));
+
+    let (a_left, a_right) = stereo(a);
+    let (b_left, b_right) = stereo(b);
+    let sample = |channel: &[f32], i: usize| channel.get(i).copied().unwrap_or(0.0);
+    for i in 0..produced {
+        let t = i as f32 / frames as f32;
+        let (fade_out, fade_in) = ((1.0 - t).sqrt(), t.sqrt());
+        out_left[i] = sample(a_left, i) * fade_out + sample(b_left, i) * fade_in;
+        out_right[i] = sample(a_right, i) * fade_out + sample(b_right, i) * fade_in;
+    }
+    produced
+}
+
+fn stereo(decoder: &Decoder) -> (&[f32], &[f32]) {
+    let left = decoder.channel(0).unwrap_or(&[]);
+    (left, decoder.channel(1).unwrap_or(left))
+}
@@ -0,0 +1,38 @@
+use std::collections::VecDeque;
+
+// Removes a constant offset with a one-pole high-pass, y[n] = x[n] - x[n-1] + R * y[n-1].
+//
+// With R = 0.995 the corner sits around 35 Hz at 44.1 kHz, low enough to leave music alone;
+// an offset decays to 1/e within about 200 samples. The previous input and output of each
+// channel carry over between blocks.
+pub struct DcFilter {
+    last_input: Vec<f32>,
+    last_output: Vec<f32>,
+}
+
+const R: f32 = 0.995;
+
+impl DcFilter {
+    pub fn new(channels: usize) -> DcFilter {
+        DcFilter { last_input: vec![0.0; channels], last_output: vec![0.0; channels] }
+    }
+
+    /// Forgets the carried-over state, for when the input jumps.
+    pub fn reset(&mut self) {
+        self.last_input.iter_mut().for_each(|x| *x = 0.0);
+        self.last_output.iter_mut().for_each(|y| *y = 0.0);
+    }
+
+    /// Filters the samples from `start` on in every output.
+    pub fn process(&mut self, outputs: &mut [VecDeque<f32>], start: usize) {
+        let state = self.last_input.iter_mut().zip(self.last_output.iter_mut());
+        for (output, (x1, y1)) in outputs.iter_mut().zip(state) {
+            for s in output.range_mut(start..) {
+                let y = *s - *x1 + R * *y1;
+                *x1 = *s;
+                *y1 = y;
+                *s = y;
+            }
+        }
+    }
+}
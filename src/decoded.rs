@@ -0,0 +1,269 @@
+use std::convert::TryFrom;
+
+#[cfg(feature = "wasm")]
+use js_sys::{Float32Array, Uint8Array};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::convert::sample_to_f32;
+use crate::error::DecodeError;
+use crate::wav;
+
+/// A whole stream decoded at once by `Decoder::decode_all`.
+///
+/// Besides handing out whole channels it can be played back with `pull`, which reads on from a
+/// position, forwards or backwards, and can loop a region indefinitely.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct DecodedAudio {
+    sample_rate: u32,
+    bit_depth: u32,
+    // The integers decoded from the stream, which `to_wav` and `to_pcm_bytes` write as they are.
+    samples: Vec<Vec<i32>>,
+    channels: Vec<Vec<f32>>,
+    // Playback state for `pull`.
+    position: usize,
+    // The part `pull` plays, all of it unless `trim_silence` cut it down.
+    start: usize,
+    end: usize,
+    loop_region: Option<(usize, usize)>,
+    reverse: bool,
+    buffers: Vec<Vec<f32>>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl DecodedAudio {
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn bit_depth(&self) -> u32 {
+        self.bit_depth
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.channels.len() as u32
+    }
+
+    /// Returns the number of samples in each channel.
+    pub fn frames(&self) -> usize {
+        self.channels.first().map_or(0, Vec::len)
+    }
+
+    /// Returns a copy of channel `index` as normalized floats.
+    #[cfg(feature = "wasm")]
+    pub fn get_channel(&self, index: u32) -> Result<Float32Array, DecodeError> {
+        let channel = self.channel(index as usize)
+            .ok_or(DecodeError::NoChannel { index, channels: self.channels() })?;
+
+        Ok(Float32Array::from(channel))
+    }
+
+    /// Returns sample `index` of channel `channel`, or NaN when either is out of range.
+    pub fn get_sample(&self, channel: u32, index: u64) -> f32 {
+        self.channels.get(channel as usize)
+            .and_then(|samples| samples.get(usize::try_from(index).ok()?))
+            .copied()
+            .unwrap_or(f32::NAN)
+    }
+
+    /// Returns the largest absolute sample over all channels.
+    pub fn peak(&self) -> f32 {
+        self.channels.iter().flatten().fold(0.0, |peak, s| peak.max(s.abs()))
+    }
+
+    /// Scales all channels by one gain so the peak lands on `target_dbfs`, and returns that gain.
+    ///
+    /// Silent audio is left as it is and returns 1.0. `to_wav` and `to_pcm_bytes` then write the
+    /// scaled samples rounded to the stream's bit depth.
+    pub fn normalize_peak(&mut self, target_dbfs: f32) -> f32 {
+        let peak = self.peak();
+        if peak == 0.0 {
+            return 1.0;
+        }
+
+        let gain = 10f32.powf(target_dbfs / 20.0) / peak;
+        self.channels.iter_mut().flatten().for_each(|s| *s *= gain);
+        let full_scale = (1u64 << (self.bit_depth - 1)) as f64;
+        self.samples.iter_mut().flatten().for_each(|s| {
+            *s = (*s as f64 * gain as f64).round().clamp(-full_scale, full_scale - 1.0) as i32;
+        });
+        gain
+    }
+
+    /// Makes `pull` skip the silence at both ends: everything before the first and after the
+    /// last sample louder than `threshold_dbfs` on any channel. Returns the `[start, end)` frame
+    /// range that is left, which is empty for silent audio.
+    ///
+    /// The samples stay in place, so `get_channel` and `to_wav` still return all of them.
+    pub fn trim_silence(&mut self, threshold_dbfs: f32) -> Box<[usize]> {
+        let threshold = 10f32.powf(threshold_dbfs / 20.0);
+        let loud = |i: usize| self.channels.iter().any(|channel| channel[i].abs() > threshold);
+
+        let frames = self.frames();
+        match (0..frames).find(|&i| loud(i)) {
+            Some(start) => {
+                let end = (start..frames).rev().find(|&i| loud(i)).map_or(start, |last| last + 1);
+                self.start = start;
+                self.end = end;
+            }
+            None => {
+                self.start = 0;
+                self.end = 0;
+            }
+        }
+        Box::new([self.start, self.end])
+    }
+
+    /// Returns the frame `pull` reads next, or in reverse the frame after it.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Moves the position `pull` reads from, clamped to the end of the audio.
+    pub fn seek(&mut self, frame: usize) {
+        self.position = frame.min(self.frames());
+    }
+
+    /// Makes `pull` play backwards from the position towards the start, frame by frame.
+    ///
+    /// A loop region then jumps from its start back to its end.
+    pub fn set_reverse(&mut self, enabled: bool) {
+        self.reverse = enabled;
+    }
+
+    /// Makes `pull` jump back to `start_sample` whenever it reaches `end_sample`, so it never
+    /// runs out. A position past the region jumps into it on the next pull.
+    pub fn set_loop(&mut self, start_sample: u64, end_sample: u64) -> Result<(), DecodeError> {
+        let frames = self.frames() as u64;
+        if start_sample >= end_sample || end_sample > frames {
+            return Err(DecodeError::InvalidLoop { start: start_sample, end: end_sample, frames });
+        }
+        self.loop_region = Some((start_sample as usize, end_sample as usize));
+        Ok(())
+    }
+
+    /// Lets `pull` play on to the end of the audio.
+    pub fn clear_loop(&mut self) {
+        self.loop_region = None;
+    }
+
+    /// Copies up to `size` frames from the current position into the pull buffers, wrapping
+    /// around the loop region if one is set, and returns the number copied.
+    pub fn pull(&mut self, size: usize) -> usize {
+        self.buffers.iter_mut().for_each(Vec::clear);
+        if self.reverse {
+            return self.pull_reverse(size);
+        }
+
+        let mut pulled = 0;
+        while pulled < size {
+            let end = match self.loop_region {
+                Some((start, end)) => {
+                    if self.position >= end {
+                        self.position = start;
+                    }
+                    end
+                }
+                None => {
+                    self.position = self.position.max(self.start);
+                    self.end
+                }
+            };
+            let len = (size - pulled).min(end.saturating_sub(self.position));
+            if len == 0 {
+                break;
+            }
+
+            let range = self.position..self.position + len;
+            for (channel, buffer) in self.channels.iter().zip(self.buffers.iter_mut()) {
+                buffer.extend_from_slice(&channel[range.clone()]);
+            }
+            self.position += len;
+            pulled += len;
+        }
+        pulled
+    }
+
+    /// Returns the samples of channel `index` copied by the last `pull`.
+    #[cfg(feature = "wasm")]
+    pub fn get_pulled(&self, index: u32) -> Result<Float32Array, DecodeError> {
+        let pulled = self.pulled(index as usize)
+            .ok_or(DecodeError::NoChannel { index, channels: self.channels() })?;
+
+        Ok(Float32Array::from(pulled))
+    }
+
+    /// Encodes the audio as a PCM WAV file at the stream's bit depth, rounded up to whole bytes.
+    #[cfg(feature = "wasm")]
+    pub fn to_wav(&self) -> Result<Uint8Array, DecodeError> {
+        Ok(Uint8Array::from(self.wav_bytes()?.as_slice()))
+    }
+
+    /// Returns the samples as raw PCM, the data of `to_wav` without its header.
+    ///
+    /// Frames follow each other with the channels interleaved in FLAC order. Each sample is a
+    /// little-endian signed integer of 2, 3 or 4 bytes for depths up to 16, 24 and 32 bits,
+    /// depths in between left-justified; 8-bit audio is one unsigned byte offset by 128.
+    #[cfg(feature = "wasm")]
+    pub fn to_pcm_bytes(&self) -> Uint8Array {
+        Uint8Array::from(self.pcm_bytes().as_slice())
+    }
+}
+
+impl DecodedAudio {
+    pub(crate) fn new(sample_rate: u32, bit_depth: u32, samples: Vec<Vec<i32>>) -> DecodedAudio {
+        let channels: Vec<Vec<f32>> = samples.iter()
+            .map(|channel| channel.iter().map(|&s| sample_to_f32(s, bit_depth)).collect())
+            .collect();
+        let buffers = vec![Vec::new(); channels.len()];
+        let end = channels.first().map_or(0, Vec::len);
+        DecodedAudio { sample_rate, bit_depth, samples, channels, position: 0, start: 0, end, loop_region: None, reverse: false, buffers }
+    }
+
+    // `pull` with time running backwards: the frames before the position, last one first.
+    fn pull_reverse(&mut self, size: usize) -> usize {
+        let mut pulled = 0;
+        while pulled < size {
+            let start = match self.loop_region {
+                Some((start, end)) => {
+                    if self.position <= start || self.position > end {
+                        self.position = end;
+                    }
+                    start
+                }
+                None => {
+                    self.position = self.position.min(self.end);
+                    self.start
+                }
+            };
+            let len = (size - pulled).min(self.position.saturating_sub(start));
+            if len == 0 {
+                break;
+            }
+
+            let range = self.position - len..self.position;
+            for (channel, buffer) in self.channels.iter().zip(self.buffers.iter_mut()) {
+                buffer.extend(channel[range.clone()].iter().rev());
+            }
+            self.position -= len;
+            pulled += len;
+        }
+        pulled
+    }
+
+    pub fn channel(&self, index: usize) -> Option<&[f32]> {
+        self.channels.get(index).map(Vec::as_slice)
+    }
+
+    pub fn pulled(&self, index: usize) -> Option<&[f32]> {
+        self.buffers.get(index).map(Vec::as_slice)
+    }
+
+    pub fn wav_bytes(&self) -> Result<Vec<u8>, DecodeError> {
+        wav::write(self.sample_rate, self.bit_depth, &self.samples)
+    }
+
+    pub fn pcm_bytes(&self) -> Vec<u8> {
+        wav::pcm(self.bit_depth, &self.samples)
+    }
+}
@@ -0,0 +1,42 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Noise added before quantizing float samples in `pull_i16`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DitherMode {
+    None,
+    /// Uniform noise of ±0.5 LSB.
+    Rectangular,
+    /// The sum of two uniform noises, ±1 LSB with a triangular distribution.
+    TriangularPDF,
+}
+
+// A xorshift generator with a fixed seed, so the same stream always dithers the same way.
+pub struct Dither {
+    pub mode: DitherMode,
+    state: u32,
+}
+
+impl Dither {
+    pub fn new(mode: DitherMode) -> Dither {
+        Dither { mode, state: 0x9e37_79b9 }
+    }
+
+    /// Returns the noise for the next sample in LSBs.
+    pub fn noise(&mut self) -> f32 {
+        match self.mode {
+            DitherMode::None => 0.0,
+            DitherMode::Rectangular => self.uniform(),
+            DitherMode::TriangularPDF => self.uniform() + self.uniform(),
+        }
+    }
+
+    // Uniform in -0.5..0.5.
+    fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state >> 8) as f32 / (1 << 24) as f32 - 0.5
+    }
+}
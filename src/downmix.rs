@@ -0,0 +1,37 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
+// ITU-R BS.775 style fold-down: front left/right pass at unity, the centre and
+// the surrounds are attenuated by 3 dB, a single back centre is split at -6 dB
+// into both sides and the LFE channel is dropped. The result is not
+// renormalized, so dense surround material can exceed full scale.
+const C: f32 = FRAC_1_SQRT_2;
+const BC: f32 = 0.5;
+
+const FL: (f32, f32) = (1.0, 0.0);
+const FR: (f32, f32) = (0.0, 1.0);
+const FC: (f32, f32) = (C, C);
+const LFE: (f32, f32) = (0.0, 0.0);
+const SL: (f32, f32) = (C, 0.0);
+const SR: (f32, f32) = (0.0, C);
+
+/// Returns the default (left, right) gain of every input channel, in FLAC channel order.
+///
+/// | channels | layout                              |
+/// |----------|-------------------------------------|
+/// | 3        | FL FR FC                            |
+/// | 4        | FL FR BL BR                         |
+/// | 5        | FL FR FC BL BR                      |
+/// | 6        | FL FR FC LFE BL BR                  |
+/// | 7        | FL FR FC LFE BC SL SR               |
+/// | 8        | FL FR FC LFE BL BR SL SR            |
+pub fn coefficients(channels: u32) -> &'static [(f32, f32)] {
+    match channels {
+        3 => &[FL, FR, FC],
+        4 => &[FL, FR, SL, SR],
+        5 => &[FL, FR, FC, SL, SR],
+        6 => &[FL, FR, FC, LFE, SL, SR],
+        7 => &[FL, FR, FC, LFE, (BC, BC), SL, SR],
+        8 => &[FL, FR, FC, LFE, SL, SR, SL, SR],
+        _ => &[FL, FR],
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+use std::io;
+
+#[cfg(feature = "wasm")]
+use js_sys::Reflect;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{JsCast, JsValue};
+
+/// The frame parameters that have to stay constant throughout a stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamParameters {
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub bits_per_sample: u32,
+}
+
+impl fmt::Display for StreamParameters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} Hz, {} channels, {} bits", self.sample_rate, self.channels, self.bits_per_sample)
+    }
+}
+
+/// Everything that can go wrong while decoding.
+///
+/// Crosses into JavaScript as an `Error` whose `code` property holds `DecodeError::code`, so
+/// callers can tell failures apart without matching on the message.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The stream does not start with `fLaC`; holds the four bytes found instead and, when it
+    /// is recognizable, the kind of file it looks like.
+    BadMagic { found: u32, format: Option<&'static str> },
+    /// The metadata ended without a STREAMINFO block and no frame header followed it.
+    MissingStreamInfo,
+    /// There is no STREAMINFO block and the frame headers defer the sample rate or bit depth to it.
+    StreamInfoRequired,
+    /// The operation needs a mono or stereo output; holds the channel count of the stream.
+    UnsupportedChannels(u32),
+    /// A channel index past the output channels.
+    NoChannel { index: u32, channels: u32 },
+    /// The input ended in the middle of the metadata.
+    Io(io::Error),
+    /// Claxon rejected the stream.
+    Claxon(claxon::Error),
+    /// The Ogg container is malformed or does not hold FLAC.
+    Ogg(&'static str),
+    /// Seeking needs a SEEKTABLE or the whole stream buffered.
+    NoSeekTable,
+    /// The seek point lies past the pushed input; holds its byte offset.
+    NotBuffered(u64),
+    /// The stream has no MD5 signature to verify against.
+    NoMd5,
+    /// `finish_verify` was called without `set_verify(true)`.
+    VerifyDisabled,
+    /// The audio was not decoded in order from the start.
+    VerifyIncomplete,
+    /// A frame, or a stream appended in gapless mode, does not match the stream's sample rate,
+    /// channel count or bit depth.
+    ParameterChange { expected: StreamParameters, found: StreamParameters },
+    /// A frame was decoded without consuming input, which would make `push` loop forever.
+    NoProgress,
+    /// The audio does not fit the 4 GB size limit of a WAV file; holds the PCM data length.
+    WavTooLarge(u64),
+    /// A JavaScript callback threw; holds the message of what it threw.
+    Callback(String),
+    /// A loop region that is empty or ends past the audio.
+    InvalidLoop { start: u64, end: u64, frames: u64 },
+    /// The stream length is unknown and no complete frame is buffered to take it from.
+    UnknownDuration,
+    /// `init_async` was called after a logger had already been installed.
+    AlreadyInitialized,
+    /// The WebAssembly memory could not be reached to create a view into it.
+    Memory,
+    /// A downmix matrix whose length is not two rows of the stream's channel count.
+    DownmixMatrix { len: usize, expected: usize },
+    /// The stream parameters lie outside what FLAC allows; names the field and holds its value.
+    InvalidStreamInfo { field: &'static str, value: u32 },
+    /// A setting was given a value it cannot take, such as NaN.
+    InvalidParameter { name: &'static str, value: f32 },
+    /// A byte range given to `new_headerless_at` holds no frame that decodes completely.
+    NoFrame,
+}
+
+impl DecodeError {
+    /// Returns a stable identifier for the kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DecodeError::BadMagic { .. } => "bad_magic",
+            DecodeError::MissingStreamInfo => "missing_stream_info",
+            DecodeError::StreamInfoRequired => "stream_info_required",
+            DecodeError::UnsupportedChannels(_) => "unsupported_channels",
+            DecodeError::NoChannel { .. } => "no_channel",
+            DecodeError::Io(_) => "io",
+            DecodeError::Claxon(_) => "claxon",
+            DecodeError::Ogg(_) => "ogg",
+            DecodeError::NoSeekTable => "no_seek_table",
+            DecodeError::NotBuffered(_) => "not_buffered",
+            DecodeError::NoMd5 => "no_md5",
+            DecodeError::VerifyDisabled => "verify_disabled",
+            DecodeError::VerifyIncomplete => "verify_incomplete",
+            DecodeError::ParameterChange { .. } => "parameter_change",
+            DecodeError::NoProgress => "no_progress",
+            DecodeError::WavTooLarge(_) => "wav_too_large",
+            DecodeError::Callback(_) => "callback",
+            DecodeError::InvalidLoop { .. } => "invalid_loop",
+            DecodeError::UnknownDuration => "unknown_duration",
+            DecodeError::AlreadyInitialized => "already_initialized",
+            DecodeError::Memory => "memory",
+            DecodeError::DownmixMatrix { .. } => "downmix_matrix",
+            DecodeError::InvalidStreamInfo { .. } => "invalid_stream_info",
+            DecodeError::InvalidParameter { .. } => "invalid_parameter",
+            DecodeError::NoFrame => "no_frame",
+        }
+    }
+
+    // Wraps whatever a JS callback threw.
+    #[cfg(feature = "wasm")]
+    pub(crate) fn thrown(thrown: JsValue) -> Self {
+        let message = match thrown.dyn_ref::<js_sys::Error>() {
+            Some(error) => error.message().into(),
+            None => thrown.as_string().unwrap_or_else(|| format!("{:?}", thrown)),
+        };
+        DecodeError::Callback(message)
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::BadMagic { format: Some(format), .. } => write!(f, "Expected FLAC, found what looks like {}", format),
+            DecodeError::BadMagic { found, format: None } => {
+                write!(f, "Expected FLAC, but the data starts with \"{}\" instead of \"fLaC\"", found.to_be_bytes().escape_ascii())
+            }
+            DecodeError::MissingStreamInfo => write!(f, "Missing stream info"),
+            DecodeError::StreamInfoRequired => write!(f, "Missing stream info, and the frame headers do not give the sample rate and bit depth"),
+            DecodeError::UnsupportedChannels(channels) => write!(f, "Stream has {} channels, use get_channel instead", channels),
+            DecodeError::NoChannel { index, channels } => write!(f, "No channel {}, output has {} channels", index, channels),
+            DecodeError::Io(err) => write!(f, "{}", err),
+            DecodeError::Claxon(err) => write!(f, "{}", err),
+            DecodeError::Ogg(reason) => write!(f, "{}", reason),
+            DecodeError::NoSeekTable => write!(f, "Stream has no seek table and is not fully buffered"),
+            DecodeError::NotBuffered(offset) => write!(f, "Seek point at byte {} is not buffered", offset),
+            DecodeError::NoMd5 => write!(f, "Stream has no MD5 signature"),
+            DecodeError::VerifyDisabled => write!(f, "Verification is not enabled"),
+            DecodeError::VerifyIncomplete => write!(f, "Audio was not decoded in order from the start, enable verification before decoding and do not seek"),
+            DecodeError::ParameterChange { expected, found } => write!(f, "Stream changed from {} to {}", expected, found),
+            DecodeError::NoProgress => write!(f, "Decoding a frame did not consume any input"),
+            DecodeError::WavTooLarge(len) => write!(f, "{} bytes of PCM data do not fit in a WAV file", len),
+            DecodeError::Callback(message) => write!(f, "Callback threw: {}", message),
+            DecodeError::InvalidLoop { start, end, frames } => write!(f, "Cannot loop {}..{}, audio has {} frames", start, end, frames),
+            DecodeError::UnknownDuration => write!(f, "Stream length is unknown and no complete frame is buffered"),
+            DecodeError::AlreadyInitialized => write!(f, "A logger is already installed"),
+            DecodeError::Memory => write!(f, "WebAssembly memory is not accessible"),
+            DecodeError::DownmixMatrix { len, expected } => write!(f, "Downmix matrix has {} gains, expected {}", len, expected),
+            DecodeError::InvalidStreamInfo { field, value } => write!(f, "Stream info gives {} as {}, which FLAC does not allow", field, value),
+            DecodeError::InvalidParameter { name, value } => write!(f, "The {} cannot be {}", name, value),
+            DecodeError::NoFrame => write!(f, "No complete frame found in the given bytes"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<io::Error> for DecodeError {
+    fn from(err: io::Error) -> Self {
+        DecodeError::Io(err)
+    }
+}
+
+impl From<claxon::Error> for DecodeError {
+    fn from(err: claxon::Error) -> Self {
+        match err {
+            claxon::Error::IoError(err) => DecodeError::Io(err),
+            err => DecodeError::Claxon(err),
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl From<DecodeError> for JsValue {
+    fn from(err: DecodeError) -> Self {
+        let error = js_sys::Error::new(&err.to_string());
+        Reflect::set(&error, &"code".into(), &err.code().into()).expect("set property on an Error");
+        error.into()
+    }
+}
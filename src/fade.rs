@@ -0,0 +1,40 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// The shape of the fades set with `Decoder::set_fade`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FadeCurve {
+    /// The gain rises in a straight line.
+    Linear,
+    /// The gain follows a square root, keeping the perceived loudness steadier.
+    EqualPower,
+}
+
+// Gain envelopes over the first `in_samples` and the last `out_samples` of a stream.
+#[derive(Clone, Copy, Debug)]
+pub struct Fade {
+    pub in_samples: u64,
+    pub out_samples: u64,
+    pub curve: FadeCurve,
+}
+
+impl Fade {
+    /// Returns the gain for the sample at `position` of a stream `total` samples long; the fade
+    /// out is skipped when the length is unknown (0).
+    pub fn gain(&self, position: u64, total: u64) -> f32 {
+        let fade_in = match self.in_samples {
+            0 => 1.0,
+            len => (position as f64 / len as f64).min(1.0),
+        };
+        let fade_out = match (self.out_samples, total) {
+            (0, _) | (_, 0) => 1.0,
+            (len, total) => (total.saturating_sub(position + 1) as f64 / len as f64).min(1.0),
+        };
+        let linear = (fade_in * fade_out) as f32;
+        match self.curve {
+            FadeCurve::Linear => linear,
+            FadeCurve::EqualPower => linear.sqrt(),
+        }
+    }
+}
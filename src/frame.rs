@@ -0,0 +1,39 @@
+use claxon::frame::Block;
+
+/// One frame handed out by `Decoder::frames`, as the integers decoded from the stream.
+///
+/// Output settings such as gain, downmixing and the output format do not apply to it.
+pub struct Frame {
+    block: Block,
+    start: u64,
+}
+
+impl Frame {
+    pub(crate) fn new(block: Block, start: u64) -> Frame {
+        Frame { block, start }
+    }
+
+    /// Returns the samples of `channel`, or `None` past the last channel.
+    pub fn channel(&self, channel: usize) -> Option<&[i32]> {
+        if channel < self.channels() {
+            Some(self.block.channel(channel as u32))
+        } else {
+            None
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.block.channels() as usize
+    }
+
+    /// Returns the number of samples in each channel.
+    pub fn samples(&self) -> usize {
+        self.block.duration() as usize
+    }
+
+    /// Returns the sample the frame starts at, as its header numbers it, counted across gapless
+    /// joins.
+    pub fn start_sample(&self) -> u64 {
+        self.start
+    }
+}
@@ -0,0 +1,180 @@
+// Parses FLAC frame headers without decoding the frame behind them.
+//
+// Claxon keeps its own header parser private, so this one is used to find frame boundaries
+// and to peek at frame parameters before handing the bytes to claxon.
+
+/// The fields of a frame header; `None` means "as in STREAMINFO".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// Whether the stream uses variable block sizes, so `number` counts samples rather than frames.
+    pub variable: bool,
+    pub block_size: u32,
+    pub sample_rate: Option<u32>,
+    pub channels: u32,
+    pub bits_per_sample: Option<u32>,
+    pub number: u64,
+    /// The length of the header in bytes, including its CRC-8.
+    pub len: usize,
+}
+
+impl FrameHeader {
+    /// Returns the first sample of the frame, given the block size of a fixed-blocksize stream.
+    pub fn first_sample(&self, fixed_block_size: u32) -> u64 {
+        if self.variable { self.number } else { self.number * fixed_block_size as u64 }
+    }
+}
+
+pub enum Parse {
+    Header(FrameHeader),
+    /// The bytes so far look like a header but it does not fit in the data.
+    Incomplete,
+    Invalid,
+}
+
+pub fn parse(data: &[u8]) -> Parse {
+    if data.len() < 2 {
+        return if data.iter().all(|&b| b == 0xff) { Parse::Incomplete } else { Parse::Invalid };
+    }
+    if data[0] != 0xff || data[1] & 0xfe != 0xf8 {
+        return Parse::Invalid;
+    }
+    if data.len() < 5 {
+        return Parse::Incomplete;
+    }
+
+    let variable = data[1] & 1 != 0;
+    let block_code = data[2] >> 4;
+    let rate_code = data[2] & 0x0f;
+    let channel_code = data[3] >> 4;
+    let bits_code = (data[3] >> 1) & 0x07;
+    if block_code == 0 || rate_code == 0x0f || channel_code > 10 || bits_code == 3 || data[3] & 1 != 0 {
+        return Parse::Invalid;
+    }
+
+    let mut pos = 4;
+    let number = match read_utf8_number(data, &mut pos) {
+        Some(Some(number)) => number,
+        Some(None) => return Parse::Invalid,
+        None => return Parse::Incomplete,
+    };
+
+    let extra = |pos: usize, len: usize| -> Option<u32> {
+        data.get(pos..pos + len).map(|bytes| bytes.iter().fold(0, |value, &b| (value << 8) | b as u32))
+    };
+
+    let block_size = match block_code {
+        1 => 192,
+        2..=5 => 576 << (block_code - 2),
+        6 | 7 => {
+            let len = (block_code - 5) as usize;
+            match extra(pos, len) {
+                Some(value) => {
+                    pos += len;
+                    value + 1
+                }
+                None => return Parse::Incomplete,
+            }
+        }
+        _ => 256 << (block_code - 8),
+    };
+
+    let sample_rate = match rate_code {
+        0 => None,
+        1 => Some(88_200),
+        2 => Some(176_400),
+        3 => Some(192_000),
+        4 => Some(8_000),
+        5 => Some(16_000),
+        6 => Some(22_050),
+        7 => Some(24_000),
+        8 => Some(32_000),
+        9 => Some(44_100),
+        10 => Some(48_000),
+        11 => Some(96_000),
+        _ => {
+            let len = if rate_code == 12 { 1 } else { 2 };
+            let scale = match rate_code { 12 => 1000, 13 => 1, _ => 10 };
+            match extra(pos, len) {
+                Some(value) => {
+                    pos += len;
+                    Some(value * scale)
+                }
+                None => return Parse::Incomplete,
+            }
+        }
+    };
+
+    let channels = if channel_code < 8 { channel_code as u32 + 1 } else { 2 };
+    let bits_per_sample = match bits_code {
+        0 => None,
+        1 => Some(8),
+        2 => Some(12),
+        4 => Some(16),
+        5 => Some(20),
+        6 => Some(24),
+        _ => Some(32),
+    };
+
+    match data.get(pos) {
+        None => Parse::Incomplete,
+        Some(&crc) if crc != crc8(&data[..pos]) => Parse::Invalid,
+        Some(_) => Parse::Header(FrameHeader {
+            variable,
+            block_size,
+            sample_rate,
+            channels,
+            bits_per_sample,
+            number,
+            len: pos + 1,
+        }),
+    }
+}
+
+/// Returns whether a frame header can give the sample rate and bit depth itself, rather than
+/// deferring them to STREAMINFO.
+pub fn codes_stream_parameters(sample_rate: u32, bits_per_sample: u32) -> bool {
+    let rate = [88_200, 176_400, 192_000, 8_000, 16_000, 22_050, 24_000, 32_000, 44_100, 48_000, 96_000].contains(&sample_rate)
+        || (sample_rate.is_multiple_of(1000) && sample_rate / 1000 <= 0xff)
+        || sample_rate <= 0xffff
+        || (sample_rate.is_multiple_of(10) && sample_rate / 10 <= 0xffff);
+    rate && [8, 12, 16, 20, 24, 32].contains(&bits_per_sample)
+}
+
+/// Returns the offset of the next plausible frame header at or after `from`, or of a header
+/// that may turn out valid once more data arrives.
+pub fn find_sync(data: &[u8], from: usize, channels: u32) -> Option<usize> {
+    (from..data.len()).find(|&pos| match parse(&data[pos..]) {
+        Parse::Header(header) => header.channels == channels,
+        Parse::Incomplete => true,
+        Parse::Invalid => false,
+    })
+}
+
+// The frame or sample number, coded like UTF-8 but up to 36 bits wide. Returns `None` when the
+// data ends early and `Some(None)` when the coding is invalid.
+fn read_utf8_number(data: &[u8], pos: &mut usize) -> Option<Option<u64>> {
+    let first = *data.get(*pos)?;
+    let ones = first.leading_ones();
+    let extra = match ones {
+        0 => 0,
+        2..=7 => ones as usize - 1,
+        _ => return Some(None),
+    };
+    let bytes = data.get(*pos + 1..*pos + 1 + extra)?;
+
+    let mut number = (first & (0x7f >> ones)) as u64;
+    for &b in bytes {
+        if b & 0xc0 != 0x80 {
+            return Some(None);
+        }
+        number = (number << 6) | (b & 0x3f) as u64;
+    }
+    *pos += 1 + extra;
+    Some(Some(number))
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |crc, &b| {
+        (0..8).fold(crc ^ b, |crc, _| if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 })
+    })
+}
@@ -0,0 +1,45 @@
+use std::cell::Cell;
+use std::io::{self, Cursor};
+
+use claxon::input::ReadBytes;
+
+/// A cursor over the buffered input whose position stays readable while a `FrameReader` owns it.
+///
+/// `FrameReader` only hands its input back through `into_inner`, so this lets one reader decode
+/// every buffered frame while the decoder still learns where each frame ended.
+pub struct SharedCursor<'a> {
+    data: &'a [u8],
+    pos: &'a Cell<u64>,
+}
+
+impl<'a> SharedCursor<'a> {
+    pub fn new(data: &'a [u8], pos: &'a Cell<u64>) -> SharedCursor<'a> {
+        SharedCursor { data, pos }
+    }
+
+    fn with<T>(&mut self, read: impl FnOnce(&mut Cursor<&[u8]>) -> T) -> T {
+        let mut cursor = Cursor::new(self.data);
+        cursor.set_position(self.pos.get());
+        let result = read(&mut cursor);
+        self.pos.set(cursor.position());
+        result
+    }
+}
+
+impl<'a> ReadBytes for SharedCursor<'a> {
+    fn read_u8(&mut self) -> io::Result<u8> {
+        self.with(|cursor| cursor.read_u8())
+    }
+
+    fn read_u8_or_eof(&mut self) -> io::Result<Option<u8>> {
+        self.with(|cursor| cursor.read_u8_or_eof())
+    }
+
+    fn read_into(&mut self, buffer: &mut [u8]) -> io::Result<()> {
+        self.with(|cursor| cursor.read_into(buffer))
+    }
+
+    fn skip(&mut self, amount: u32) -> io::Result<()> {
+        self.with(|cursor| cursor.skip(amount))
+    }
+}
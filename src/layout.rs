@@ -0,0 +1,39 @@
+// The speaker positions of FLAC's fixed channel assignments.
+//
+// FLAC orders its channels like the bits of the WAVEFORMATEXTENSIBLE channel mask, which is
+// also the order Web Audio expects, so the channels never have to be moved, only named.
+
+/// Returns the speaker of every channel for `channels` channels, in FLAC channel order.
+pub fn speakers(channels: u32) -> &'static [&'static str] {
+    match channels {
+        1 => &["FC"],
+        2 => &["FL", "FR"],
+        3 => &["FL", "FR", "FC"],
+        4 => &["FL", "FR", "BL", "BR"],
+        5 => &["FL", "FR", "FC", "BL", "BR"],
+        6 => &["FL", "FR", "FC", "LFE", "BL", "BR"],
+        7 => &["FL", "FR", "FC", "LFE", "BC", "SL", "SR"],
+        8 => &["FL", "FR", "FC", "LFE", "BL", "BR", "SL", "SR"],
+        _ => &[],
+    }
+}
+
+/// Returns the WAVEFORMATEXTENSIBLE `dwChannelMask` for `channels` channels.
+pub fn channel_mask(channels: u32) -> u32 {
+    speakers(channels).iter().fold(0, |mask, &speaker| mask | speaker_bit(speaker))
+}
+
+fn speaker_bit(speaker: &str) -> u32 {
+    match speaker {
+        "FL" => 0x1,
+        "FR" => 0x2,
+        "FC" => 0x4,
+        "LFE" => 0x8,
+        "BL" => 0x10,
+        "BR" => 0x20,
+        "BC" => 0x100,
+        "SL" => 0x200,
+        "SR" => 0x400,
+        _ => 0,
+    }
+}
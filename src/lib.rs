@@ -1,166 +1,2517 @@
+use std::cell::Cell;
 use std::collections::VecDeque;
+use std::convert::TryFrom;
 use std::io::{Cursor, ErrorKind};
 
-use claxon::frame::FrameReader;
+use claxon::frame::{Block, FrameReader};
 use claxon::input::ReadBytes;
-use claxon::metadata::{MetadataBlock, MetadataBlockReader, StreamInfo};
-use js_sys::{Float32Array, WebAssembly};
-use log::{debug, error, Level};
+use claxon::metadata::StreamInfo;
+#[cfg(feature = "wasm")]
+use js_sys::{Array, Float32Array, Float64Array, Int16Array, Int32Array, Object, Reflect, Uint8Array, WebAssembly};
+use log::{debug, error, warn};
+#[cfg(feature = "wasm")]
+use log::Level;
+#[cfg(feature = "wasm")]
 use wasm_bindgen::JsCast;
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-use crate::utils::set_panic_hook;
+pub use crate::crossfade::crossfade;
+pub use crate::decoded::DecodedAudio;
+pub use crate::dither::DitherMode;
+pub use crate::fade::FadeCurve;
+pub use crate::frame::Frame;
+pub use crate::error::{DecodeError, StreamParameters};
+use crate::frame_header::{FrameHeader, Parse};
+use crate::input::SharedCursor;
+use crate::dc_filter::DcFilter;
+use crate::dither::Dither;
+use crate::fade::Fade;
+use crate::md5::Md5;
+use crate::metadata::{id3v2_len, read_metadata, sniff_format, Metadata};
+use crate::ogg::OggDemuxer;
+use crate::resample::Resampler;
+use crate::sink::{DownmixSink, FloatSink, IntSink, SampleSink};
+#[cfg(feature = "wasm")]
+use crate::utils::{object, set_panic_hook};
 
+pub use crate::metadata::{Application, BlockHeader, CueIndex, CueSheet, CueTrack, Picture, SeekPoint};
+pub use crate::replay_gain::{ReplayGain, ReplayGainMode};
+pub use crate::stats::DecodeStats;
+
+mod convert;
+mod crossfade;
+mod dc_filter;
+mod decoded;
+mod dither;
+mod downmix;
+mod error;
+mod fade;
+mod frame;
+mod frame_header;
+mod input;
+mod layout;
+mod md5;
+mod metadata;
+mod ogg;
+mod replay_gain;
+mod resample;
+mod sink;
+mod stats;
+#[cfg(feature = "wasm")]
 mod utils;
+mod wav;
+
+#[cfg(test)]
+#[path = "../tests/common/mod.rs"]
+mod common;
 
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub fn init(debug_log_level: bool) {
     set_panic_hook();
     console_log::init_with_level(if debug_log_level { Level::Debug } else { Level::Info }).expect("init");
 }
 
+/// Like `init`, but returns a promise that rejects with an `already_initialized` error instead
+/// of panicking when called twice.
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
+pub fn init_async(debug_log_level: bool) -> js_sys::Promise {
+    set_panic_hook();
+    match console_log::init_with_level(if debug_log_level { Level::Debug } else { Level::Info }) {
+        Ok(()) => js_sys::Promise::resolve(&JsValue::UNDEFINED),
+        Err(_) => js_sys::Promise::reject(&DecodeError::AlreadyInitialized.into()),
+    }
+}
+
+/// Sample representation buffered by `push` and written by `pull`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Normalized floats in `-1.0..1.0`.
+    F32,
+    /// Normalized floats quantized to 16 bits on `pull`.
+    I16,
+    /// The integers decoded from the stream, untouched by downmixing.
+    I32,
+    /// Like `I32`, but shifted to 24 significant bits, right-justified and sign-extended, whatever
+    /// the depth of the stream.
+    I24,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct Decoder {
-    input: Option<Vec<u8>>,
-    output: VecDeque<(f32, f32)>,
-    left: Vec<f32>,
-    right: Vec<f32>,
+    // Frame data pushed and not yet released. Decoding only moves `input_pos`, and pushes drop
+    // the decoded prefix before appending, so only an incomplete frame is ever moved. With
+    // `keep_input` the decoded frames are kept instead, so `seek` can go back to them.
+    input: Vec<u8>,
+    input_pos: usize,
+    // Offset of `input[0]` from the first frame, which moves forward when decoded bytes are dropped.
+    input_start: u64,
+    output: Vec<VecDeque<f32>>,
+    buffers: Vec<Vec<f32>>,
+    buffers_i16: Vec<Vec<i16>>,
+    output_i32: Vec<VecDeque<i32>>,
+    buffers_i32: Vec<Vec<i32>>,
+    buffers_f64: Vec<Vec<f64>>,
+    interleaved: Vec<f32>,
+    mono: Vec<f32>,
+    format: OutputFormat,
     stream_info: StreamInfo,
+    seek_points: Vec<SeekPoint>,
+    metadata_blocks: Vec<BlockHeader>,
+    tags: Vec<(String, String)>,
+    vendor: Option<String>,
+    pictures: Vec<Picture>,
+    applications: Vec<Application>,
+    cue_sheet: Option<CueSheet>,
+    downmix: bool,
+    // The (left, right) gain of every input channel set by `set_downmix_matrix`.
+    downmix_matrix: Option<Vec<(f32, f32)>>,
+    swap_channels: bool,
+    // Bit n set negates output channel n.
+    invert_polarity: u32,
+    error_recovery: bool,
+    crc_failures: u32,
+    stats: DecodeStats,
+    follow_parameter_changes: bool,
+    gapless: bool,
+    keep_input: bool,
+    // The streams joined in gapless mode, the first one included once there is a second.
+    segments: Vec<Segment>,
+    max_decode_per_push: Option<usize>,
+    output_limit: Option<usize>,
+    replay_gain: ReplayGain,
+    replay_gain_mode: ReplayGainMode,
+    gain: f32,
+    playback_rate: f64,
+    // How far past the front of `output` the next varispeed pull reads, in frames.
+    playback_phase: f64,
+    soft_clip: bool,
+    limiter: bool,
+    dither: Dither,
+    fade: Option<Fade>,
+    peaks: Vec<f32>,
+    target_sample_rate: Option<u32>,
+    resampler: Option<Resampler>,
+    dc_filter: Option<DcFilter>,
+    // An output channel and its samples already drained by `pull_into_left` or `pull_into_right`
+    // for the other side, waiting for it to ask for them.
+    pending: (usize, VecDeque<f32>),
+    // Frames per channel allocated for the pull buffers.
+    buffer_capacity: usize,
+    // Sample storage handed back and forth with claxon so frames don't allocate.
+    frame_buffer: Vec<i32>,
+    next_sample: u64,
+    // The sample number in the header of the last decoded frame, counted across gapless joins.
+    last_frame_start: u64,
+    // The header of the first decoded frame, for `is_subset`.
+    first_frame: Option<FrameHeader>,
+    // The sample the first frame starts at, past 0 for a range cut from the middle of a file.
+    start_sample: u64,
+    // Frames handed out by the pull methods, or the seek position plus those since.
+    pulled: u64,
+    seek_target: Option<u64>,
+    verify: Option<Verify>,
+    ogg: Option<OggDemuxer>,
 }
 
-#[wasm_bindgen]
+// Receives the decoder and the number of samples pulled after each frame.
+type FrameCallback<'a> = &'a mut dyn FnMut(&Decoder, usize) -> Result<(), DecodeError>;
+
+// A stream joined in gapless mode. Its frame headers number samples from its own start, so the
+// samples before it are added to them.
+struct Segment {
+    // Where its frames start, counting the input dropped from the front.
+    start: u64,
+    first_sample: u64,
+    // The block size of a fixed-blocksize stream, which its frame numbers count in.
+    block_size: u32,
+    samples: Option<u64>,
+}
+
+// Hashes decoded audio for comparison with the STREAMINFO MD5, as long as frames arrive in order from the start.
+struct Verify {
+    md5: Md5,
+    next_sample: u64,
+    contiguous: bool,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Decoder {
-    pub fn new(buffer: Box<[u8]>) -> Result<Decoder, JsValue> {
-        const FLAC_HEADER: u32 = 0x66_4c_61_43;
+    /// Parses the metadata in `buffer`; frame data after it is decoded by the first `push`.
+    ///
+    /// The pull buffers start with room for one block of the stream's largest block size.
+    pub fn new(buffer: Box<[u8]>) -> Result<Decoder, DecodeError> {
+        Self::open(buffer, None)
+    }
 
-        debug!("Trying to create FLAC Decoder from {} bytes", buffer.len());
+    /// Like `new`, but allocates room for `frames` pulled frames per channel up front instead
+    /// of one block. Pulling more still works, the buffers grow to fit.
+    pub fn new_with_capacity(buffer: Box<[u8]>, frames: usize) -> Result<Decoder, DecodeError> {
+        Self::open(buffer, Some(frames))
+    }
 
-        let mut cursor = Cursor::new(buffer);
-        let header = cursor.read_be_u32().map_err(|e| e.to_string())?;
-        if header != FLAC_HEADER {
-            return Err(format!("Wrong FLAC Header, got: {} expected: {}", header, FLAC_HEADER).into());
+    /// Decodes bare frames whose stream parameters travel out of band, without the `fLaC`
+    /// marker and metadata blocks that `new` expects.
+    ///
+    /// The whole buffer is taken as frame data. The block size comes from the first frame, and
+    /// the length, MD5 signature and tags are unknown.
+    pub fn new_raw(buffer: Box<[u8]>, sample_rate: u32, channels: u32, bits_per_sample: u32) -> Result<Decoder, DecodeError> {
+        if !(1..=8).contains(&channels) {
+            return Err(DecodeError::UnsupportedChannels(channels));
         }
+        debug!("Creating raw FLAC Decoder from {} bytes", buffer.len());
 
-        let stream_info = {
-            let mut maybe_stream_info = None;
-            let metadata_reader = MetadataBlockReader::new(&mut cursor);
-            for item in metadata_reader {
-                let item = item.map_err(|e| e.to_string())?;
-                match item {
-                    MetadataBlock::StreamInfo(si) => {
-                        maybe_stream_info = Some(si);
-                    }
-                    _ => {}
-                }
-            }
-
-            maybe_stream_info.ok_or_else(|| "Missing stream info")?
+        let block_size = match frame_header::parse(&buffer) {
+            Parse::Header(header) => header.block_size.min(u16::MAX as u32) as u16,
+            _ => 4096,
         };
+        let stream_info = StreamInfo {
+            min_block_size: block_size,
+            max_block_size: block_size,
+            min_frame_size: None,
+            max_frame_size: None,
+            sample_rate,
+            channels,
+            bits_per_sample,
+            samples: None,
+            md5sum: [0; 16],
+        };
+        check_stream_info(&stream_info)?;
+        Ok(Self::with_stream(buffer.into_vec(), stream_info, Metadata::default(), None, None))
+    }
 
-        let position = cursor.position() as usize;
-        let remaining = &cursor.into_inner()[position..];
+    /// Decodes a byte range cut from the middle of a file, such as one fetched with an HTTP range
+    /// request, with the stream parameters taken from the file's start.
+    ///
+    /// `stream_info_bytes` is either the head of the file from `fLaC` through the metadata, or
+    /// the bare 34-byte STREAMINFO body. `frame_bytes` may start and end mid-frame: decoding syncs
+    /// to the first frame that decodes completely, and `start_sample` reports where it begins.
+    /// `first_sample` is a lower bound for that sample, from a seek table for instance, that
+    /// keeps bytes which merely look like a frame header from being taken for one; pass 0 when
+    /// it is unknown. The seek table is dropped, as its offsets do not apply to the range.
+    pub fn new_headerless_at(stream_info_bytes: Box<[u8]>, frame_bytes: Box<[u8]>, first_sample: u64) -> Result<Decoder, DecodeError> {
+        debug!("Creating FLAC Decoder for a range of {} bytes", frame_bytes.len());
 
-        let input = if remaining.len() > 0 {
-            Some(remaining.into_iter().cloned().collect())
+        let mut cursor = Cursor::new(stream_info_bytes);
+        let mut metadata = if cursor.get_ref().starts_with(b"fLaC") {
+            cursor.set_position(4);
+            let mut metadata = read_metadata(&mut cursor)?;
+            metadata.seek_points.clear();
+            metadata
         } else {
-            None
+            let length = cursor.get_ref().len() as u32;
+            match claxon::metadata::read_metadata_block(&mut cursor, 0, length)? {
+                claxon::metadata::MetadataBlock::StreamInfo(stream_info) => Metadata { stream_info: Some(stream_info), ..Metadata::default() },
+                _ => return Err(DecodeError::MissingStreamInfo),
+            }
         };
+        let stream_info = metadata.stream_info.take().ok_or(DecodeError::MissingStreamInfo)?;
+        check_stream_info(&stream_info)?;
+
+        let (pos, sample) = first_frame(&frame_bytes, &stream_info, first_sample).ok_or(DecodeError::NoFrame)?;
+        debug!("Range starts with the frame at byte {}, sample {}", pos, sample);
+        let mut decoder = Self::with_stream(frame_bytes[pos..].to_vec(), stream_info, metadata, None, None);
+        decoder.start_sample = sample;
+        decoder.next_sample = sample;
+        Ok(decoder)
+    }
+
+    /// Returns the sample the first frame of the input starts at, which is 0 except for a
+    /// decoder made by `new_headerless_at`.
+    pub fn start_sample(&self) -> u64 {
+        self.start_sample
+    }
+
+    /// Like `new`, but calls `on_metadata` once with the stream's metadata before returning.
+    ///
+    /// The callback receives `{stream_info, tags, pictures, has_seek_table}`, with the first
+    /// three as `stream_info`, `get_tags` and `get_pictures` return them. If it throws, construction
+    /// fails with a `callback` error.
+    #[cfg(feature = "wasm")]
+    pub fn new_with_callbacks(buffer: Box<[u8]>, on_metadata: Option<js_sys::Function>) -> Result<Decoder, DecodeError> {
+        Self::with_metadata_callback(buffer, |decoder| match &on_metadata {
+            Some(on_metadata) => on_metadata.call1(&JsValue::NULL, &decoder.metadata_object()).map(drop).map_err(DecodeError::thrown),
+            None => Ok(()),
+        })
+    }
+
+    /// Decodes a complete file in one go, for when the whole buffer is already in memory.
+    ///
+    /// A truncated final frame is dropped rather than reported.
+    pub fn decode_all(buffer: Box<[u8]>) -> Result<DecodedAudio, DecodeError> {
+        // The smallest a frame can be: a 6-byte header, a constant subframe per channel and the CRC-16.
+        const MIN_FRAME_BYTES: usize = 10;
 
-        let left = Vec::with_capacity(16 * 1024);
-        let right = Vec::with_capacity(16 * 1024);
+        let mut decoder = Decoder::new(buffer)?;
+        // The integers are kept as decoded, which floats cannot do for 32-bit samples.
+        decoder.set_output_format(OutputFormat::I32);
+        // Growing the buffers one block at a time reallocates and copies them over and over for
+        // a long file. The length comes from the header, so it is capped at what the input could
+        // hold rather than trusted to reserve gigabytes for a small file, and a length that still
+        // cannot be reserved falls back to that growth rather than aborting.
+        if let Some(total) = decoder.stream_info.samples.filter(|&total| total > 0) {
+            let frames = decoder.input.len() / MIN_FRAME_BYTES + 1;
+            let bound = frames.saturating_mul(decoder.stream_info.max_block_size as usize);
+            let total = usize::try_from(total).unwrap_or(usize::MAX).min(bound);
+            for output in decoder.output_i32.iter_mut() {
+                let _ = output.try_reserve_exact(total);
+            }
+        }
+        decoder.push(&[])?;
 
-        Ok(Self { input, output: Default::default(), left, right, stream_info })
+        // Whatever the reservation overshot is returned rather than kept for the audio's lifetime.
+        let samples = std::mem::take(&mut decoder.output_i32).into_iter()
+            .map(|output| {
+                let mut channel = Vec::from(output);
+                channel.shrink_to_fit();
+                channel
+            })
+            .collect();
+        Ok(DecodedAudio::new(decoder.sample_rate(), decoder.bit_depth(), samples))
     }
 
     pub fn bit_depth(&self) -> u32 {
         self.stream_info.bits_per_sample
     }
 
+    /// Returns the rate of the buffered output, which is the target rate while resampling.
     pub fn sample_rate(&self) -> u32 {
-        self.stream_info.sample_rate
+        match &self.resampler {
+            Some(_) => self.target_sample_rate.unwrap_or(self.stream_info.sample_rate),
+            None => self.stream_info.sample_rate,
+        }
+    }
+
+    pub fn channels(&self) -> u32 {
+        self.stream_info.channels
+    }
+
+    /// Returns the speaker of every output channel in order, such as `["FL", "FR", "FC", "LFE",
+    /// "BL", "BR"]` for 5.1, which is also the order of WAV and Web Audio.
+    #[cfg(feature = "wasm")]
+    pub fn channel_layout(&self) -> Array {
+        self.speakers().iter().map(|&speaker| JsValue::from(speaker)).collect()
+    }
+
+    /// Returns the smallest block in sample frames; the last frame of a stream may be shorter.
+    pub fn min_block_size(&self) -> u32 {
+        self.stream_info.min_block_size as u32
+    }
+
+    /// Returns the largest block in sample frames, which is enough to hold any single frame.
+    pub fn max_block_size(&self) -> u32 {
+        self.stream_info.max_block_size as u32
+    }
+
+    /// Returns the smallest frame in bytes, or `undefined` when the encoder did not record it.
+    pub fn min_frame_size(&self) -> Option<u32> {
+        self.stream_info.min_frame_size
+    }
+
+    /// Returns the largest frame in bytes, or `undefined` when the encoder did not record it.
+    pub fn max_frame_size(&self) -> Option<u32> {
+        self.stream_info.max_frame_size
+    }
+
+    /// Returns the number of frames in the stream according to STREAMINFO, or 0 when unknown.
+    pub fn total_samples(&self) -> u64 {
+        self.stream_info.samples.unwrap_or(0)
+    }
+
+    /// Returns whether the stream keeps to the FLAC Subset, the profile hardware decoders and
+    /// streaming need, as far as it can be told without decoding the subframes.
+    ///
+    /// Checks the largest block size against the limit for the sample rate, 4608 up to 48 kHz and
+    /// 16384 above, that samples have at most 24 bits, and that the sample rate and bit depth can
+    /// be given in frame headers. The first frame is checked too once it is decoded or buffered:
+    /// its header must not defer them to STREAMINFO or exceed the block size limit. The limits on
+    /// LPC order and Rice partition order apply inside the subframes, which the decoder does not
+    /// expose, so they are not checked.
+    pub fn is_subset(&self) -> bool {
+        let info = &self.stream_info;
+        let max_block_size = if info.sample_rate <= 48_000 { 4608 } else { 16384 };
+        // Until a frame is decoded the next buffered one is the first.
+        let first_frame = self.first_frame.or_else(|| match frame_header::parse(&self.input[self.input_pos..]) {
+            Parse::Header(header) => Some(header),
+            _ => None,
+        });
+        let frame = first_frame.is_none_or(|header| {
+            header.sample_rate.is_some() && header.bits_per_sample.is_some() && header.block_size <= max_block_size
+        });
+        frame && info.max_block_size as u32 <= max_block_size
+            && info.bits_per_sample <= 24
+            && frame_header::codes_stream_parameters(info.sample_rate, info.bits_per_sample)
+    }
+
+    /// Returns all STREAMINFO fields in one object: `{sample_rate, channels, bits_per_sample,
+    /// total_samples, min_block_size, max_block_size, min_frame_size, max_frame_size, md5}`.
+    ///
+    /// Values the stream leaves unknown are `undefined`, and `md5` is a `Uint8Array`.
+    #[cfg(feature = "wasm")]
+    pub fn stream_info(&self) -> JsValue {
+        let info = &self.stream_info;
+        object(&[
+            ("sample_rate", info.sample_rate.into()),
+            ("channels", info.channels.into()),
+            ("bits_per_sample", info.bits_per_sample.into()),
+            ("total_samples", info.samples.map(|samples| samples as f64).into()),
+            ("min_block_size", self.min_block_size().into()),
+            ("max_block_size", self.max_block_size().into()),
+            ("min_frame_size", info.min_frame_size.into()),
+            ("max_frame_size", info.max_frame_size.into()),
+            ("md5", self.md5().into()),
+        ])
+    }
+
+    /// Returns the number of frames in the stream, taking it from the last buffered frame
+    /// when STREAMINFO leaves it unknown, as piped encoders do.
+    ///
+    /// The stream has to be pushed up to its end, or the result falls short. Only the last frame
+    /// is decoded, and the length is then kept as if STREAMINFO had given it.
+    pub fn compute_duration(&mut self) -> Result<u64, DecodeError> {
+        if let Some(samples) = self.stream_info.samples.filter(|&samples| samples > 0) {
+            return Ok(samples);
+        }
+
+        let samples = self.last_frame_end().ok_or(DecodeError::UnknownDuration)?;
+        debug!("Computed stream length of {} samples", samples);
+        self.stream_info.samples = Some(samples);
+        Ok(samples)
+    }
+
+    /// Returns the stream length in seconds, or 0.0 when the length or sample rate is unknown.
+    pub fn duration_seconds(&self) -> f64 {
+        match self.stream_info.sample_rate {
+            0 => 0.0,
+            rate => self.total_samples() as f64 / rate as f64,
+        }
     }
 
-    pub fn push(&mut self, data: Box<[u8]>) -> Result<usize, JsValue> {
+    /// Returns the sample index just past the last decoded frame.
+    ///
+    /// This is decode progress rather than playback: `push` and `seek` move it, `pull` does not,
+    /// and `reset` sets it back to 0.
+    pub fn sample_position(&self) -> u64 {
+        self.next_sample
+    }
+
+    /// Returns the sample the last decoded frame starts at, as its header numbers it, or 0 before
+    /// the first frame. Past a gapless join the samples of the streams before are added.
+    ///
+    /// Unlike `sample_position` this comes from the stream rather than from counting, so comparing
+    /// it with the end of the frame before shows gaps, and after a `seek` it shows where decoding
+    /// resumed. A `push_with_callback` callback can read it for the frame it was called for.
+    pub fn last_frame_start_sample(&self) -> u64 {
+        self.last_frame_start
+    }
+
+    /// Returns `sample_position` in seconds.
+    pub fn time_position(&self) -> f64 {
+        match self.stream_info.sample_rate {
+            0 => 0.0,
+            rate => self.next_sample as f64 / rate as f64,
+        }
+    }
+
+    /// Returns the MD5 signature of the unencoded audio, or `undefined` when the encoder left it unset.
+    #[cfg(feature = "wasm")]
+    pub fn md5(&self) -> Option<Uint8Array> {
+        self.md5sum().map(|md5| Uint8Array::from(&md5[..]))
+    }
+
+    /// Hashes the decoded audio while pushing so `finish_verify` can check it against the MD5 signature.
+    ///
+    /// Verification only succeeds when enabled before the first frame is decoded and the whole
+    /// stream is then decoded in order; seeking in between makes `finish_verify` fail.
+    pub fn set_verify(&mut self, enabled: bool) {
+        self.verify = if enabled {
+            self.verify.take().or_else(|| Some(Verify { md5: Md5::new(), next_sample: 0, contiguous: true }))
+        } else {
+            None
+        };
+    }
+
+    /// Compares the audio hashed so far with the STREAMINFO MD5 signature.
+    pub fn finish_verify(&self) -> Result<bool, DecodeError> {
+        let expected = self.md5sum().ok_or(DecodeError::NoMd5)?;
+        let verify = self.verify.as_ref().ok_or(DecodeError::VerifyDisabled)?;
+        if !verify.contiguous {
+            return Err(DecodeError::VerifyIncomplete);
+        }
+
+        Ok(verify.md5.clone().finish() == *expected)
+    }
+
+    /// Skips corrupt frames instead of failing `push`, resuming at the next frame header found.
+    ///
+    /// The samples of skipped frames are lost, and the skipped byte ranges are logged.
+    pub fn set_error_recovery(&mut self, enabled: bool) {
+        self.error_recovery = enabled;
+    }
+
+    /// Returns the lifetime counters as `{frames, samples, bytes, pushes}`; see `DecodeStats`.
+    #[cfg(feature = "wasm")]
+    pub fn stats(&self) -> JsValue {
+        object(&[
+            ("frames", (self.stats.frames as f64).into()),
+            ("samples", (self.stats.samples as f64).into()),
+            ("bytes", (self.stats.bytes as f64).into()),
+            ("pushes", (self.stats.pushes as f64).into()),
+        ])
+    }
+
+    /// Returns how many frames failed their header CRC-8 or frame CRC-16 since the decoder was
+    /// created or reset, including frames skipped by error recovery.
+    pub fn crc_failures(&self) -> u32 {
+        self.crc_failures
+    }
+
+    /// Adopts the sample rate, channel count or bit depth of frames that change them mid-stream.
+    ///
+    /// By default such a frame fails `push` with a `parameter_change` error. When following
+    /// changes, a new channel count re-lays the output and discards samples not pulled yet.
+    pub fn set_follow_parameter_changes(&mut self, enabled: bool) {
+        self.follow_parameter_changes = enabled;
+    }
+
+    /// Accepts another complete FLAC stream pushed after the last frame of the current one, and
+    /// keeps appending its audio to the buffered output without a gap.
+    ///
+    /// The sample rate, channel count and bit depth have to match, otherwise `push` fails with a
+    /// `parameter_change` error. Only the first stream's metadata is kept, and the sample
+    /// position keeps counting across streams. So do frame start samples and seeking once
+    /// decoding has passed a join.
+    pub fn set_gapless(&mut self, enabled: bool) {
+        self.gapless = enabled;
+    }
+
+    /// Keeps the decoded input when more is pushed, so `seek` can go back into a stream pushed in
+    /// chunks. The input then grows with the stream until `free_input`.
+    pub fn set_keep_input(&mut self, enabled: bool) {
+        self.keep_input = enabled;
+    }
+
+    /// Resamples float output to `rate` while decoding, or stops resampling when `None`.
+    ///
+    /// Uses linear interpolation, which is cheap and seamless across pushes but lets a little
+    /// aliasing through when downsampling. `sample_rate` then reports the target rate, while
+    /// sample positions, seeking and `total_samples` stay in the stream's own rate.
+    /// `OutputFormat::I32` and `I24` output is never resampled.
+    pub fn set_target_sample_rate(&mut self, rate: Option<u32>) {
+        self.target_sample_rate = rate.filter(|&rate| rate > 0);
+        self.layout_output();
+    }
+
+    /// Makes `push` stop once it has decoded at least `frames` sample frames, leaving the rest of
+    /// the input buffered; `None` decodes everything.
+    ///
+    /// Whole FLAC frames are always decoded, so a push may overshoot by up to one block. Call
+    /// `push` again, with or without new data, to continue.
+    pub fn set_max_decode_per_push(&mut self, frames: Option<usize>) {
+        self.max_decode_per_push = frames;
+    }
+
+    /// Makes `push` stop before the buffered output would grow past `frames`, so a slow consumer
+    /// holds back decoding; `None` removes the limit.
+    ///
+    /// The unconsumed input stays buffered and the next `push` resumes once `pull` has made room.
+    /// A frame is always decoded into empty output, even when it is larger than the limit. With
+    /// `set_max_decode_per_push` as well, `push` stops at whichever limit is reached first.
+    pub fn set_output_limit(&mut self, frames: Option<usize>) {
+        self.output_limit = frames;
+    }
+
+    /// Folds streams with more than two channels down to stereo while decoding.
+    ///
+    /// Changing the output layout discards samples that have not been pulled yet.
+    pub fn set_downmix(&mut self, enabled: bool) {
+        self.downmix = enabled;
+        self.layout_output();
+    }
+
+    /// Replaces the built-in surround fold-down with `matrix`, the row-major 2 × channels gains
+    /// from every input channel in FLAC order to the left and then the right output.
+    ///
+    /// Only affects frames decoded afterwards, and only while `set_downmix` is on.
+    pub fn set_downmix_matrix(&mut self, matrix: &[f32]) -> Result<(), DecodeError> {
+        let channels = self.channels() as usize;
+        if matrix.len() != 2 * channels {
+            return Err(DecodeError::DownmixMatrix { len: matrix.len(), expected: 2 * channels });
+        }
+        let (left, right) = matrix.split_at(channels);
+        self.downmix_matrix = Some(left.iter().copied().zip(right.iter().copied()).collect());
+        Ok(())
+    }
+
+    /// Goes back to the built-in fold-down.
+    pub fn clear_downmix_matrix(&mut self) {
+        self.downmix_matrix = None;
+    }
+
+    /// Swaps the first two output channels while decoding, for files with left and right mixed up.
+    ///
+    /// Only affects frames decoded afterwards.
+    pub fn set_swap_channels(&mut self, enabled: bool) {
+        self.swap_channels = enabled;
+    }
+
+    /// Negates the samples of every output channel whose bit is set in `channel_mask`, bit 0
+    /// being the left channel, while decoding. Applied after `set_swap_channels`.
+    ///
+    /// Only affects frames decoded afterwards.
+    pub fn set_invert_polarity(&mut self, channel_mask: u32) {
+        self.invert_polarity = channel_mask;
+    }
+
+    /// Removes any DC offset from float and 16-bit output with a high-pass filter while decoding.
+    ///
+    /// The filter settles within a few hundred samples, and starts over after a seek or reset.
+    pub fn set_dc_filter(&mut self, enabled: bool) {
+        self.dc_filter = if enabled { Some(DcFilter::new(self.output.len())) } else { None };
+    }
+
+    /// Selects what `push` buffers and `pull` writes.
+    ///
+    /// Changing the output layout discards samples that have not been pulled yet.
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.format = format;
+        self.layout_output();
+    }
+
+    /// Appends `data` to the input, decodes the complete frames in it and returns how many
+    /// frames per channel were decoded.
+    ///
+    /// The frames decoded by earlier pushes are dropped first, so a stream pushed in chunks holds
+    /// about a frame of input, but `seek` cannot go back into it unless `set_keep_input` is on.
+    /// Pushing no data drops nothing, so a stream handed to `new` whole stays seekable.
+    pub fn push(&mut self, data: &[u8]) -> Result<usize, DecodeError> {
         debug!("Pushing {} bytes", data.len());
-        let mut input = self.input.take().unwrap_or_default();
-        input.extend(data.iter());
+        self.stats.pushes += 1;
+        self.append_input(data)?;
+        self.decode(None)
+    }
 
-        let mut total = 0;
-        let mut pos = 0;
-        let left_shift = 32 - self.bit_depth();
+    /// Like `push`, but hands over every frame as soon as it is decoded instead of buffering it.
+    ///
+    /// `on_frame` is called once per frame with the number of samples and an array holding a
+    /// copy of each channel, as `pull` would write it in the current output format; nothing is
+    /// left to pull afterwards. If it throws, `push_with_callback` fails with a `callback` error.
+    #[cfg(feature = "wasm")]
+    pub fn push_with_callback(&mut self, data: &[u8], on_frame: js_sys::Function) -> Result<usize, DecodeError> {
+        self.push_with_frames(data, |decoder, frames| {
+            let channels: Array = match decoder.format {
+                OutputFormat::F32 => decoder.buffers.iter().map(|b| JsValue::from(Float32Array::from(&b[..]))).collect(),
+                OutputFormat::I16 => decoder.buffers_i16.iter().map(|b| JsValue::from(Int16Array::from(&b[..]))).collect(),
+                OutputFormat::I32 | OutputFormat::I24 => decoder.buffers_i32.iter().map(|b| JsValue::from(Int32Array::from(&b[..]))).collect(),
+            };
+            on_frame.call2(&JsValue::NULL, &frames.into(), &channels).map(drop).map_err(DecodeError::thrown)
+        })
+    }
 
-        loop {
-            let mut reader = FrameReader::new(Cursor::new(&input[pos..]));
-            match reader.read_next_or_eof(Vec::new()) {
-                Ok(Some(block)) => {
-                    for (l, r) in block.stereo_samples() {
-                        let l = ((l << left_shift) as u32).wrapping_add(0x80000000);
-                        let r = ((r << left_shift) as u32).wrapping_add(0x80000000);
-                        let l = (l as f32) / 2147483648.0 - 1.0;
-                        let r = (r as f32) / 2147483648.0 - 1.0;
+    /// Decodes only the next frame of the input pushed so far and returns the samples it produced,
+    /// leaving the rest for `push`. This confirms the stream decodes and has the first audio ready
+    /// at the cost of a single frame.
+    ///
+    /// Returns 0 without decoding anything when no complete frame is buffered.
+    pub fn prime(&mut self) -> Result<usize, DecodeError> {
+        let max_decode_per_push = self.max_decode_per_push.replace(1);
+        let result = self.decode(None);
+        self.max_decode_per_push = max_decode_per_push;
+        result
+    }
 
-                        self.output.push_back((l, r));
-                    }
+    /// Returns the number of pushed bytes not decoded yet, usually the start of a partial frame.
+    pub fn buffered_input_len(&self) -> usize {
+        self.input.len() - self.input_pos
+    }
 
-                    total += block.duration() as usize;
-                    pos += reader.into_inner().position() as usize;
-                }
-                Ok(None) => {
-                    break;
-                }
-                Err(err) => {
-                    match &err {
-                        claxon::Error::IoError(err) if err.kind() == ErrorKind::UnexpectedEof => {
-                            // this is ok, just break
-                            break;
-                        }
-                        _ => {}
-                    }
-                    error!("Error while decoding: {:?}", &err);
-                    return Err(err.to_string().into());
-                }
+    /// Returns the number of samples in the next frame to decode, read from its header without
+    /// decoding it, or `undefined` when its header is not completely buffered yet.
+    pub fn peek_next_frame_duration(&self) -> Option<u32> {
+        match frame_header::parse(&self.input[self.input_pos..]) {
+            Parse::Header(header) => Some(header.block_size),
+            Parse::Incomplete | Parse::Invalid => None,
+        }
+    }
+
+    /// Returns roughly how many more bytes to push before the next frame is sure to be complete.
+    ///
+    /// This is a hint, not an exact count: it assumes the next frame is as large as the largest
+    /// in the stream, which is the STREAMINFO maximum if the encoder recorded one and otherwise
+    /// the size of an uncompressed block.
+    pub fn bytes_needed(&self) -> usize {
+        let max_frame_size = match self.stream_info.max_frame_size {
+            Some(size) => size as usize,
+            None => {
+                let info = &self.stream_info;
+                let bits = info.max_block_size as usize * info.channels as usize * (info.bits_per_sample as usize + 1);
+                // Frame header, subframe headers and the CRC-16 come on top of the samples.
+                bits.div_ceil(8) + 16 + info.channels as usize + 2
+            }
+        };
+        max_frame_size.saturating_sub(self.buffered_input_len())
+    }
+
+    /// Drops the buffered input and the samples not pulled yet, keeping their allocations.
+    ///
+    /// Stream info, tags and settings survive, as does the sample position: the next `push`
+    /// should continue at a frame boundary. Seeking needs the whole stream to be pushed again.
+    pub fn flush(&mut self) {
+        // The input that follows continues the current stream.
+        if let Some(segment) = self.segments.pop() {
+            self.segments = vec![Segment { start: 0, ..segment }];
+        }
+        self.input.clear();
+        self.input_pos = 0;
+        self.input_start = 0;
+        self.seek_target = None;
+        self.clear_output();
+    }
+
+    /// Rewinds the decoder so the same stream can be pushed again from its first frame.
+    ///
+    /// Like `flush`, but also empties the pull buffers and resets the sample position and
+    /// verification. Stream info survives, so push only the frames and not the `fLaC` header.
+    pub fn reset(&mut self) {
+        self.segments.truncate(1);
+        self.flush();
+        self.next_sample = self.start_sample;
+        self.last_frame_start = 0;
+        self.pulled = 0;
+        self.crc_failures = 0;
+        self.stats = DecodeStats::default();
+        self.buffers.iter_mut().for_each(Vec::clear);
+        self.buffers_i16.iter_mut().for_each(Vec::clear);
+        self.buffers_i32.iter_mut().for_each(Vec::clear);
+        self.buffers_f64.iter_mut().for_each(Vec::clear);
+        self.interleaved.clear();
+        self.mono.clear();
+        self.dither = Dither::new(self.dither.mode);
+        self.reset_peaks();
+        if self.verify.is_some() {
+            self.verify = None;
+            self.set_verify(true);
+        }
+    }
+
+    /// Releases the input that has already been decoded, keeping only an incomplete trailing frame.
+    ///
+    /// Pushing afterwards still works and simply starts a fresh buffer, but `seek` can no longer
+    /// go back to the released frames.
+    pub fn free_input(&mut self) {
+        self.drop_decoded_input();
+        self.input.shrink_to_fit();
+    }
+
+    /// Estimates the bytes the decoder holds on to: the pushed input, the sample queues, the pull
+    /// buffers and the kept metadata. Counts allocated capacity rather than the part in use, so it
+    /// shows what `free_input` and `shrink_output_to_fit` would give back.
+    pub fn memory_usage(&self) -> usize {
+        fn bytes<T>(capacity: usize) -> usize {
+            capacity * std::mem::size_of::<T>()
+        }
+
+        let samples = self.output.iter().map(|output| bytes::<f32>(output.capacity()))
+            .chain(self.output_i32.iter().map(|output| bytes::<i32>(output.capacity())))
+            .chain(self.buffers.iter().map(|buffer| bytes::<f32>(buffer.capacity())))
+            .chain(self.buffers_i16.iter().map(|buffer| bytes::<i16>(buffer.capacity())))
+            .chain(self.buffers_i32.iter().map(|buffer| bytes::<i32>(buffer.capacity())))
+            .chain(self.buffers_f64.iter().map(|buffer| bytes::<f64>(buffer.capacity())))
+            .sum::<usize>()
+            + bytes::<f32>(self.interleaved.capacity() + self.mono.capacity() + self.pending.1.capacity())
+            + bytes::<i32>(self.frame_buffer.capacity());
+        let metadata = self.tags.iter().map(|(key, value)| key.capacity() + value.capacity()).sum::<usize>()
+            + self.pictures.iter().map(|picture| picture.data.capacity()).sum::<usize>()
+            + self.applications.iter().map(|application| application.data.capacity()).sum::<usize>()
+            + bytes::<SeekPoint>(self.seek_points.capacity());
+
+        self.input.capacity() + samples + metadata
+    }
+
+    /// Returns unused capacity of the sample queues and pull buffers to the allocator.
+    ///
+    /// The queues grow again on the next push, which then reallocates them, so this pays off after
+    /// a burst of decoding rather than between regular pushes.
+    pub fn shrink_output_to_fit(&mut self) {
+        self.output.iter_mut().for_each(VecDeque::shrink_to_fit);
+        self.output_i32.iter_mut().for_each(VecDeque::shrink_to_fit);
+        self.buffers.iter_mut().for_each(Vec::shrink_to_fit);
+        self.buffers_i16.iter_mut().for_each(Vec::shrink_to_fit);
+        self.buffers_i32.iter_mut().for_each(Vec::shrink_to_fit);
+        self.buffers_f64.iter_mut().for_each(Vec::shrink_to_fit);
+        self.interleaved.shrink_to_fit();
+        self.mono.shrink_to_fit();
+    }
+
+    /// Returns whether `seek` can work: the stream has a SEEKTABLE, or all of it is buffered so it
+    /// can be searched instead.
+    ///
+    /// A stream counts as fully buffered once the last pushed frame ends at the STREAMINFO length
+    /// and neither `free_input` nor a `push` of more data has released its start, so a stream of
+    /// unknown length never does. A SEEKTABLE only reaches back into input that is still buffered.
+    pub fn is_seekable(&self) -> bool {
+        !self.seek_points.is_empty() || self.is_fully_buffered()
+    }
+
+    /// Moves decoding to `sample` using the SEEKTABLE, discarding frames that were not pulled yet.
+    ///
+    /// Decodes forward from the nearest seek point and returns the sample reached, which is
+    /// `sample` unless the buffered input ends before it. Without a SEEKTABLE a fully buffered
+    /// stream is searched for the frame instead.
+    pub fn seek(&mut self, sample: u64) -> Result<u64, DecodeError> {
+        let (start, pos) = if self.seek_points.is_empty() {
+            if !self.is_fully_buffered() {
+                return Err(DecodeError::NoSeekTable);
             }
+            self.bisect(sample)
+        } else {
+            let (start, offset) = self.seek_points.iter()
+                .rev()
+                .find(|point| point.sample <= sample)
+                .map_or((0, 0), |point| (point.sample, point.offset));
+            let pos = offset.checked_sub(self.input_start)
+                .filter(|&pos| pos <= self.input.len() as u64)
+                .ok_or(DecodeError::NotBuffered(offset))?;
+            (start, pos as usize)
+        };
+
+        self.clear_output();
+        self.input_pos = pos;
+        self.next_sample = start;
+        self.seek_target = Some(sample);
+        self.decode(None)?;
+
+        self.pulled = sample.min(self.next_sample);
+        Ok(self.pulled)
+    }
+
+    /// Like `seek`, but takes and returns a time in seconds.
+    ///
+    /// Negative times seek to the start and times past the end of a stream of known length to its end.
+    pub fn seek_seconds(&mut self, seconds: f64) -> Result<f64, DecodeError> {
+        let rate = self.stream_info.sample_rate as f64;
+        let mut sample = (seconds.max(0.0) * rate).round() as u64;
+        if let Some(total) = self.stream_samples() {
+            sample = sample.min(total);
+        }
+
+        Ok(self.seek(sample)? as f64 / rate)
+    }
+
+    /// Returns the SEEKTABLE as `{sample_number, byte_offset, frame_samples}` objects, without placeholders.
+    #[cfg(feature = "wasm")]
+    pub fn seek_points(&self) -> Array {
+        self.seek_points.iter()
+            .map(|point| object(&[
+                ("sample_number", (point.sample as f64).into()),
+                ("byte_offset", (point.offset as f64).into()),
+                ("frame_samples", point.samples.into()),
+            ]))
+            .collect()
+    }
+
+    /// Returns the header of every metadata block in stream order as `{block_type, length,
+    /// is_last}`, including PADDING, APPLICATION and unknown blocks.
+    #[cfg(feature = "wasm")]
+    pub fn metadata_blocks(&self) -> Array {
+        self.metadata_blocks.iter()
+            .map(|block| object(&[
+                ("block_type", block.block_type.into()),
+                ("length", block.length.into()),
+                ("is_last", block.is_last.into()),
+            ]))
+            .collect()
+    }
+
+    /// Returns the Vorbis comments as an object mapping each uppercased key to an array of values.
+    #[cfg(feature = "wasm")]
+    pub fn get_tags(&self) -> JsValue {
+        let tags = Object::new();
+        for (key, value) in &self.tags {
+            let key = JsValue::from_str(key);
+            let values = Reflect::get(&tags, &key).ok()
+                .and_then(|values| values.dyn_into::<Array>().ok())
+                .unwrap_or_else(|| {
+                    let values = Array::new();
+                    Reflect::set(&tags, &key, &values).expect("set property on a plain object");
+                    values
+                });
+            values.push(&JsValue::from_str(value));
+        }
+        tags.into()
+    }
+
+    /// Returns the vendor string of the Vorbis comments, which names the encoder, or `undefined`
+    /// when the stream has no Vorbis comment block.
+    pub fn vendor(&self) -> Option<String> {
+        self.vendor.clone()
+    }
+
+    /// Returns the ReplayGain tags as `{track_gain, track_peak, album_gain, album_peak}`, missing values `undefined`.
+    #[cfg(feature = "wasm")]
+    pub fn replay_gain(&self) -> JsValue {
+        object(&[
+            ("track_gain", self.replay_gain.track_gain.into()),
+            ("track_peak", self.replay_gain.track_peak.into()),
+            ("album_gain", self.replay_gain.album_gain.into()),
+            ("album_peak", self.replay_gain.album_peak.into()),
+        ])
+    }
+
+    /// Returns the track gain in dB, or `undefined` when the tag is missing or unreadable.
+    pub fn replay_gain_track(&self) -> Option<f32> {
+        self.replay_gain.track_gain
+    }
+
+    /// Returns the album gain in dB, or `undefined` when the tag is missing or unreadable.
+    pub fn replay_gain_album(&self) -> Option<f32> {
+        self.replay_gain.album_gain
+    }
+
+    /// Returns the track peak as linear amplitude, or `undefined` when the tag is missing or unreadable.
+    pub fn replay_gain_track_peak(&self) -> Option<f32> {
+        self.replay_gain.track_peak
+    }
+
+    /// Returns the album peak as linear amplitude, or `undefined` when the tag is missing or unreadable.
+    pub fn replay_gain_album_peak(&self) -> Option<f32> {
+        self.replay_gain.album_peak
+    }
+
+    /// Scales float output by the track or album gain while decoding, limited by the peak to avoid clipping.
+    ///
+    /// Only affects frames decoded afterwards; `OutputFormat::I32` and `I24` output is never scaled.
+    pub fn set_replay_gain_mode(&mut self, mode: ReplayGainMode) {
+        self.replay_gain_mode = mode;
+    }
+
+    /// Sets a linear volume applied to float and 16-bit samples as they are pulled, 1.0 by default.
+    ///
+    /// The gain is applied at pull time, so it also affects samples that are already buffered.
+    pub fn set_gain(&mut self, linear: f32) {
+        self.gain = linear;
+    }
+
+    /// Like `set_gain`, but in decibels: -6.0 about halves the amplitude and 0.0 leaves it alone.
+    pub fn set_gain_db(&mut self, db: f32) -> Result<(), DecodeError> {
+        if !db.is_finite() {
+            return Err(DecodeError::InvalidParameter { name: "gain in dB", value: db });
+        }
+        self.gain = 10f32.powf(db / 20.0);
+        Ok(())
+    }
+
+    /// Makes float pulls read `rate` buffered frames for every frame they write, so 2.0 plays
+    /// twice as fast and an octave higher. 1.0, the default, passes frames through untouched.
+    ///
+    /// Frames in between are interpolated linearly and the read position carries over from one
+    /// pull to the next. Each written frame needs the buffered frame after it, so a pull stops
+    /// one frame short of the end of the buffered output, except at the end of a stream of
+    /// known length, where the last frame plays too. Applies to `pull` in
+    /// `OutputFormat::F32` mode and to `pull_f32`; `pulled_samples` counts the frames read.
+    pub fn set_playback_rate(&mut self, rate: f32) -> Result<(), DecodeError> {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(DecodeError::InvalidParameter { name: "playback rate", value: rate });
+        }
+        self.playback_rate = rate as f64;
+        // Pulls at the normal rate start from the front frame, so a later rate change must too.
+        if self.playback_rate == 1.0 {
+            self.playback_phase = 0.0;
         }
+        Ok(())
+    }
+
+    /// Rounds off pulled samples that the gain would push past ±1.0 instead of letting them clip.
+    pub fn set_soft_clip(&mut self, enabled: bool) {
+        self.soft_clip = enabled;
+    }
+
+    /// Folds pulled samples that gain or ReplayGain push past full scale back below ±1.0, so
+    /// transients round off instead of flat-topping.
+    ///
+    /// Below 0.9 samples pass unchanged. Above it, with `x = (|s| - 0.9) / 0.1`, the output is
+    /// `0.9 + 0.1 * x / (1 + x)`. Like `tanh` this meets the linear part with the same slope, but
+    /// it approaches ±1.0 so slowly that peaks many dB over full scale still come out distinct.
+    /// Runs after the soft clip when both are enabled.
+    pub fn set_limiter(&mut self, enabled: bool) {
+        self.limiter = enabled;
+    }
 
-        self.input = match (pos == 0, pos == input.len()) {
-            (_, true) => None,
-            (true, _) => Some(input),
-            _ => Some(input.as_slice()[pos..].into_iter().cloned().collect())
+    /// Fades float and 16-bit output in over the first `in_samples` of the stream and out over
+    /// the last `out_samples` before `total_samples`, or removes the fades when both are 0.
+    ///
+    /// Like the gain, the fades are applied at pull time, by the absolute position of each
+    /// sample, so they do not depend on how the output is pulled. Positions count stream
+    /// samples, so the fades are only exact without a target sample rate.
+    pub fn set_fade(&mut self, in_samples: u64, out_samples: u64, curve: FadeCurve) {
+        self.fade = match (in_samples, out_samples) {
+            (0, 0) => None,
+            _ => Some(Fade { in_samples, out_samples, curve }),
         };
+    }
+
+    /// Selects the noise added before `pull_i16` quantizes samples, `DitherMode::None` by default.
+    pub fn set_dither(&mut self, mode: DitherMode) {
+        self.dither.mode = mode;
+    }
+
+    /// Returns the first value of the Vorbis comment `key`, compared case-insensitively.
+    pub fn get_tag(&self, key: &str) -> Option<String> {
+        self.tags.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Returns every value of the Vorbis comment `key` in stream order, compared case-insensitively.
+    pub fn get_tag_all(&self, key: &str) -> Vec<String> {
+        self.tags.iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.get_tag("TITLE")
+    }
+
+    pub fn artist(&self) -> Option<String> {
+        self.get_tag("ARTIST")
+    }
+
+    pub fn album(&self) -> Option<String> {
+        self.get_tag("ALBUM")
+    }
+
+    /// Returns the TRACKNUMBER tag as a number, also when written as `track/total`.
+    pub fn track_number(&self) -> Option<u32> {
+        self.get_tag("TRACKNUMBER")?.split('/').next()?.trim().parse().ok()
+    }
+
+    /// Returns the DATE tag as written, usually a year or an ISO 8601 date.
+    pub fn date(&self) -> Option<String> {
+        self.get_tag("DATE")
+    }
+
+    pub fn genre(&self) -> Option<String> {
+        self.get_tag("GENRE")
+    }
+
+    /// Returns the embedded pictures as `{picture_type, mime_type, description, width, height, data}` objects.
+    #[cfg(feature = "wasm")]
+    pub fn get_pictures(&self) -> Array {
+        self.pictures.iter()
+            .map(|picture| object(&[
+                ("picture_type", picture.picture_type.into()),
+                ("mime_type", JsValue::from_str(&picture.mime_type)),
+                ("description", JsValue::from_str(&picture.description)),
+                ("width", picture.width.into()),
+                ("height", picture.height.into()),
+                ("data", Uint8Array::from(picture.data.as_slice()).into()),
+            ]))
+            .collect()
+    }
+
+    /// Returns the APPLICATION blocks as `{id, data}` objects, `id` being the four-character
+    /// registered ID.
+    #[cfg(feature = "wasm")]
+    pub fn applications(&self) -> Array {
+        self.applications.iter()
+            .map(|application| object(&[
+                ("id", JsValue::from_str(&String::from_utf8_lossy(&application.id))),
+                ("data", Uint8Array::from(application.data.as_slice()).into()),
+            ]))
+            .collect()
+    }
+
+    /// Returns the CUESHEET tracks as `{number, offset, isrc, indices}` objects, where `offset` is the
+    /// first sample of the track and `indices` holds `{number, offset}` relative to it.
+    ///
+    /// The lead-out is left out, see `cue_lead_out`. Streams without a cue sheet return an empty array.
+    #[cfg(feature = "wasm")]
+    pub fn cue_tracks(&self) -> Array {
+        let tracks = self.cue_sheet.as_ref().map_or(&[][..], |cue_sheet| &cue_sheet.tracks);
+        tracks.iter()
+            .map(|track| object(&[
+                ("number", track.number.into()),
+                ("offset", (track.offset as f64).into()),
+                ("isrc", JsValue::from_str(&track.isrc)),
+                ("indices", track.indices.iter()
+                    .map(|index| object(&[
+                        ("number", index.number.into()),
+                        ("offset", (index.offset as f64).into()),
+                    ]))
+                    .collect::<Array>()
+                    .into()),
+            ]))
+            .collect()
+    }
+
+    /// Returns the sample offset of the cue sheet's lead-out, where the last track ends.
+    pub fn cue_lead_out(&self) -> Option<u64> {
+        self.cue_sheet.as_ref().and_then(|cue_sheet| cue_sheet.lead_out)
+    }
 
-        Ok(total)
+    /// Returns the number of decoded frames waiting to be pulled.
+    pub fn available_samples(&self) -> usize {
+        match self.format {
+            OutputFormat::I32 | OutputFormat::I24 => self.output_i32.first().map_or(0, VecDeque::len),
+            _ => self.output.first().map_or(0, VecDeque::len),
+        }
+    }
+
+    /// Returns the number of frames handed out by the pull methods since the start, a seek or a
+    /// reset, counting from the seek position after a seek; a playhead for what was delivered.
+    ///
+    /// Frames pulled with `pull_into_left` and `pull_into_right` count once for the pair.
+    pub fn pulled_samples(&self) -> u64 {
+        self.pulled
+    }
+
+    /// Like `pulled_samples`, in seconds at the output sample rate, or 0.0 when it is unknown.
+    pub fn played_seconds(&self) -> f64 {
+        match self.sample_rate() {
+            0 => 0.0,
+            rate => self.pulled as f64 / rate as f64,
+        }
+    }
+
+    /// Returns the buffered output as a duration, at the output sample rate, or 0.0 when the
+    /// rate is unknown.
+    pub fn buffered_seconds(&self) -> f64 {
+        match self.sample_rate() {
+            0 => 0.0,
+            rate => self.available_samples() as f64 / rate as f64,
+        }
     }
 
+    /// Copies up to `size` buffered frames into the pull buffers of the current output format.
+    ///
+    /// The buffers grow when `size` exceeds the capacity they were created with.
     pub fn pull(&mut self, size: usize) -> usize {
-        let mut read_pos = 0;
-        for (l, r) in self.output.iter() {
-            self.left[read_pos] = *l;
-            self.right[read_pos] = *r;
+        match self.format {
+            OutputFormat::F32 => self.pull_f32(size),
+            OutputFormat::I16 => self.pull_i16(size),
+            OutputFormat::I32 | OutputFormat::I24 => self.pull_i32(size),
+        }
+    }
 
-            read_pos += 1;
-            if read_pos >= size {
-                break;
-            }
+    pub fn pull_f32(&mut self, size: usize) -> usize {
+        if self.playback_rate != 1.0 {
+            return self.pull_varispeed(size);
+        }
+
+        let frames = size.min(self.buffered_floats());
+        let gains = self.pull_gains(frames);
+        let clip = self.clip();
+        for (output, buffer) in self.output.iter_mut().zip(self.buffers.iter_mut()) {
+            buffer.clear();
+            buffer.extend(output.drain(..frames).zip(gains.iter()).map(|(s, gain)| apply_gain(s, gain, clip)));
+        }
+
+        self.pulled += frames as u64;
+        frames
+    }
+
+    /// Copies up to `size` float frames starting `offset` frames into the buffered output to the
+    /// channel buffers, like `pull` in `OutputFormat::F32` mode but without consuming them, and
+    /// returns the number copied. Gain and fades apply as they will when the frames are pulled.
+    ///
+    /// Integer formats buffer no floats, so this copies nothing in them.
+    pub fn pull_at(&mut self, offset: usize, size: usize) -> usize {
+        let buffered = self.buffered_floats();
+        let frames = size.min(buffered.saturating_sub(offset));
+        let front = self.next_sample.saturating_sub(buffered as u64);
+        let gains = self.gains(front + offset as u64, frames);
+        let clip = self.clip();
+        for (output, buffer) in self.output.iter().zip(self.buffers.iter_mut()) {
+            buffer.clear();
+            buffer.extend(output.range(offset..offset + frames).zip(gains.iter())
+                .map(|(&s, gain)| apply_gain(s, gain, clip)));
+        }
+        frames
+    }
+
+    /// Like `pull`, but quantizes the samples to 16-bit integers.
+    ///
+    /// Samples are scaled by 32768, dithered if enabled, rounded half away from zero and clamped
+    /// to the `i16` range. Like the other float pulls it finds nothing to pull in
+    /// `OutputFormat::I32` and `I24` mode, which buffer integers only; use `pull_i32` there.
+    pub fn pull_i16(&mut self, size: usize) -> usize {
+        let frames = size.min(self.buffered_floats());
+        let gains = self.pull_gains(frames);
+        let clip = self.clip();
+        let dither = &mut self.dither;
+        for (output, buffer) in self.output.iter_mut().zip(self.buffers_i16.iter_mut()) {
+            buffer.clear();
+            buffer.extend(output.drain(..frames).zip(gains.iter())
+                .map(|(s, gain)| sample_to_i16(apply_gain(s, gain, clip), dither.noise())));
         }
 
-        read_pos
+        self.pulled += frames as u64;
+        frames
     }
 
-    pub fn get_left(&self) -> Float32Array {
-        let buffer = wasm_bindgen::memory().dyn_into::<WebAssembly::Memory>().unwrap().buffer();
-        js_sys::Float32Array::new_with_byte_offset_and_length(
-            &buffer,
-            self.left.as_ptr() as u32,
-            (self.left.capacity() * 4) as u32,
-        )
+    /// Like `pull`, but copies the integers decoded in `OutputFormat::I32` or `I24` mode.
+    pub fn pull_i32(&mut self, size: usize) -> usize {
+        let frames = size.min(self.available_samples());
+        for (output, buffer) in self.output_i32.iter_mut().zip(self.buffers_i32.iter_mut()) {
+            buffer.clear();
+            buffer.extend(output.drain(..frames));
+        }
+
+        self.pulled += frames as u64;
+        frames
     }
 
-    pub fn get_right(&self) -> Float32Array {
-        let buffer = wasm_bindgen::memory().dyn_into::<WebAssembly::Memory>().unwrap().buffer();
-        js_sys::Float32Array::new_with_byte_offset_and_length(
-            &buffer,
-            self.right.as_ptr() as u32,
-            (self.right.capacity() * 4) as u32,
-        )
+    /// Like `pull`, but writes doubles.
+    ///
+    /// In `OutputFormat::I32` and `I24` mode the integers are divided by their full scale in
+    /// double precision, never passing through `f32`. The float formats widen their buffered
+    /// floats after applying gain and fades.
+    pub fn pull_f64(&mut self, size: usize) -> usize {
+        let frames = size.min(self.available_samples());
+        if self.is_integer_output() {
+            let bits = if self.format == OutputFormat::I24 { 24 } else { self.bit_depth() };
+            let full_scale = (1u64 << (bits - 1)) as f64;
+            for (output, buffer) in self.output_i32.iter_mut().zip(self.buffers_f64.iter_mut()) {
+                buffer.clear();
+                buffer.extend(output.drain(..frames).map(|s| s as f64 / full_scale));
+            }
+        } else {
+            let gains = self.pull_gains(frames);
+            let clip = self.clip();
+            for (output, buffer) in self.output.iter_mut().zip(self.buffers_f64.iter_mut()) {
+                buffer.clear();
+                buffer.extend(output.drain(..frames).zip(gains.iter())
+                    .map(|(s, gain)| apply_gain(s, gain, clip) as f64));
+            }
+        }
+
+        self.pulled += frames as u64;
+        frames
     }
-}
\ No newline at end of file
+
+    /// Copies up to `frames` buffered frames into a single buffer, channels interleaved.
+    ///
+    /// Returns the number of frames written; the buffer holds `frames * channels` samples.
+    pub fn pull_interleaved(&mut self, frames: usize) -> usize {
+        let frames = frames.min(self.buffered_floats());
+
+        let gains = self.pull_gains(frames);
+        let clip = self.clip();
+        self.interleaved.clear();
+        for (i, gain) in gains.iter().enumerate() {
+            self.interleaved.extend(self.output.iter().map(|output| apply_gain(output[i], gain, clip)));
+        }
+        for output in self.output.iter_mut() {
+            output.drain(..frames);
+        }
+
+        self.pulled += frames as u64;
+        frames
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_interleaved(&self) -> Result<Float32Array, DecodeError> {
+        f32_view(&self.interleaved)
+    }
+
+    /// Drains up to `size` buffered frames into a single buffer holding the average of all
+    /// output channels, and returns the number of frames.
+    pub fn pull_mono(&mut self, size: usize) -> usize {
+        let frames = size.min(self.buffered_floats());
+        let gains = self.pull_gains(frames);
+        let clip = self.clip();
+        let scale = 1.0 / self.output.len() as f32;
+
+        self.mono.clear();
+        self.mono.resize(frames, 0.0);
+        for output in self.output.iter_mut() {
+            for (mono, s) in self.mono.iter_mut().zip(output.drain(..frames)) {
+                *mono += s;
+            }
+        }
+        for (mono, gain) in self.mono.iter_mut().zip(gains.iter()) {
+            *mono = apply_gain(*mono * scale, gain, clip);
+        }
+
+        self.pulled += frames as u64;
+        frames
+    }
+
+    /// Returns a view of the samples written by the last `pull_mono`.
+    #[cfg(feature = "wasm")]
+    pub fn get_mono(&self) -> Result<Float32Array, DecodeError> {
+        f32_view(&self.mono)
+    }
+
+    /// Returns the highest normalized magnitude decoded on the left channel since the last `reset_peaks`.
+    pub fn peak_left(&self) -> f32 {
+        self.peaks.first().copied().unwrap_or(0.0)
+    }
+
+    /// Like `peak_left`; for mono streams this is the peak of the only channel.
+    pub fn peak_right(&self) -> f32 {
+        self.peaks.get(self.peaks.len().min(2).saturating_sub(1)).copied().unwrap_or(0.0)
+    }
+
+    /// Returns a copy of the peaks of all output channels.
+    #[cfg(feature = "wasm")]
+    pub fn peaks(&self) -> Float32Array {
+        Float32Array::from(&self.peaks[..])
+    }
+
+    /// Restarts peak metering, e.g. from a timer that lets the meter decay.
+    pub fn reset_peaks(&mut self) {
+        self.peaks.iter_mut().for_each(|peak| *peak = 0.0);
+    }
+
+    /// Returns a view of the samples written to channel `index` by the last `pull`.
+    ///
+    /// All `get_*` views alias WebAssembly memory: they go stale when the next pull overwrites
+    /// the buffer and are detached as soon as the memory grows, which any `push` may cause.
+    /// Copy the data out before calling back into the decoder, or use `copy_channel_into`.
+    #[cfg(feature = "wasm")]
+    pub fn get_channel(&self, index: u32) -> Result<Float32Array, DecodeError> {
+        let channel = self.buffers.get(index as usize)
+            .ok_or_else(|| self.no_channel(index))?;
+
+        f32_view(channel)
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_channel_i16(&self, index: u32) -> Result<Int16Array, DecodeError> {
+        let channel = self.buffers_i16.get(index as usize)
+            .ok_or_else(|| self.no_channel(index))?;
+
+        i16_view(channel)
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_left_i16(&self) -> Result<Int16Array, DecodeError> {
+        self.check_stereo()?;
+        self.get_channel_i16(0)
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_right_i16(&self) -> Result<Int16Array, DecodeError> {
+        self.check_stereo()?;
+        self.get_channel_i16(self.output_channels().min(2) - 1)
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_channel_i32(&self, index: u32) -> Result<Int32Array, DecodeError> {
+        let channel = self.buffers_i32.get(index as usize)
+            .ok_or_else(|| self.no_channel(index))?;
+
+        i32_view(channel)
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_left_i32(&self) -> Result<Int32Array, DecodeError> {
+        self.check_stereo()?;
+        self.get_channel_i32(0)
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_right_i32(&self) -> Result<Int32Array, DecodeError> {
+        self.check_stereo()?;
+        self.get_channel_i32(self.output_channels().min(2) - 1)
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_channel_f64(&self, index: u32) -> Result<Float64Array, DecodeError> {
+        let channel = self.buffers_f64.get(index as usize)
+            .ok_or_else(|| self.no_channel(index))?;
+
+        f64_view(channel)
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_left_f64(&self) -> Result<Float64Array, DecodeError> {
+        self.check_stereo()?;
+        self.get_channel_f64(0)
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn get_right_f64(&self) -> Result<Float64Array, DecodeError> {
+        self.check_stereo()?;
+        self.get_channel_f64(self.output_channels().min(2) - 1)
+    }
+
+    /// Returns the left channel pulled in `OutputFormat::I24` mode, which `get_left_i32` returns too.
+    #[cfg(feature = "wasm")]
+    pub fn get_left_i24(&self) -> Result<Int32Array, DecodeError> {
+        self.get_left_i32()
+    }
+
+    /// Returns the right channel pulled in `OutputFormat::I24` mode, which `get_right_i32` returns too.
+    #[cfg(feature = "wasm")]
+    pub fn get_right_i24(&self) -> Result<Int32Array, DecodeError> {
+        self.get_right_i32()
+    }
+
+    /// Copies the samples written to channel `index` by the last `pull` into `dest`.
+    ///
+    /// Returns the number of samples copied, which is limited by the length of `dest`.
+    pub fn copy_channel_into(&self, index: u32, dest: &mut [f32]) -> Result<usize, DecodeError> {
+        let channel = self.buffers.get(index as usize)
+            .ok_or_else(|| self.no_channel(index))?;
+
+        let len = channel.len().min(dest.len());
+        dest[..len].copy_from_slice(&channel[..len]);
+        Ok(len)
+    }
+
+    pub fn copy_left_into(&self, dest: &mut [f32]) -> Result<usize, DecodeError> {
+        self.check_stereo()?;
+        self.copy_channel_into(0, dest)
+    }
+
+    pub fn copy_right_into(&self, dest: &mut [f32]) -> Result<usize, DecodeError> {
+        self.check_stereo()?;
+        self.copy_channel_into(self.output_channels().min(2) - 1, dest)
+    }
+
+    /// Moves up to `dest.len()` buffered frames of the left channel straight into `dest`,
+    /// skipping the pull buffers, and returns the number written.
+    ///
+    /// The sides stay aligned whatever sizes they pull: frames are taken from both channels at
+    /// once, and the right channel's are kept for `pull_into_right`, which hands them out before
+    /// any newer ones. On a mono stream both sides read the one channel, so use only this one.
+    pub fn pull_into_left(&mut self, dest: &mut [f32]) -> Result<usize, DecodeError> {
+        self.check_stereo()?;
+        Ok(self.pull_channel_into(0, dest))
+    }
+
+    /// Like `pull_into_left`, for the right channel.
+    pub fn pull_into_right(&mut self, dest: &mut [f32]) -> Result<usize, DecodeError> {
+        self.check_stereo()?;
+        Ok(self.pull_channel_into(self.output_channels().min(2) as usize - 1, dest))
+    }
+
+    /// Returns the left channel of a mono or stereo stream; mono streams play the same channel on both sides.
+    #[cfg(feature = "wasm")]
+    pub fn get_left(&self) -> Result<Float32Array, DecodeError> {
+        self.check_stereo()?;
+        self.get_channel(0)
+    }
+
+    /// Returns the right channel of a mono or stereo stream; mono streams play the same channel on both sides.
+    #[cfg(feature = "wasm")]
+    pub fn get_right(&self) -> Result<Float32Array, DecodeError> {
+        self.check_stereo()?;
+        self.get_channel(self.output_channels().min(2) - 1)
+    }
+}
+
+impl Decoder {
+    fn open(buffer: Box<[u8]>, capacity: Option<usize>) -> Result<Decoder, DecodeError> {
+        const FLAC_HEADER: u32 = 0x66_4c_61_43;
+
+        debug!("Trying to create FLAC Decoder from {} bytes", buffer.len());
+
+        let mut ogg = None;
+        let buffer = if ogg::is_ogg(&buffer) {
+            debug!("Demuxing Ogg FLAC");
+            let demuxer = ogg.get_or_insert_with(OggDemuxer::default);
+            demuxer.push(&buffer).map_err(DecodeError::Ogg)?.into_boxed_slice()
+        } else {
+            buffer
+        };
+
+        let mut cursor = Cursor::new(buffer);
+        if let Some(len) = id3v2_len(cursor.get_ref()) {
+            debug!("Skipping {} byte ID3v2 tag", len);
+            cursor.set_position(len);
+        }
+        let header = cursor.read_be_u32()?;
+        if header != FLAC_HEADER {
+            let format = sniff_format(&cursor.get_ref()[cursor.position() as usize - 4..]);
+            return Err(DecodeError::BadMagic { found: header, format });
+        }
+
+        let mut metadata = read_metadata(&mut cursor)?;
+        let position = cursor.position() as usize;
+        let input = cursor.into_inner()[position..].to_vec();
+
+        let stream_info = match metadata.stream_info.take() {
+            Some(stream_info) => stream_info,
+            None => stream_info_from_frame(&input)?,
+        };
+        check_stream_info(&stream_info)?;
+        Ok(Self::with_stream(input, stream_info, metadata, ogg, capacity))
+    }
+
+    fn with_stream(
+        input: Vec<u8>,
+        stream_info: StreamInfo,
+        metadata: Metadata,
+        ogg: Option<OggDemuxer>,
+        capacity: Option<usize>,
+    ) -> Decoder {
+        let mut decoder = Self {
+            input,
+            input_pos: 0,
+            input_start: 0,
+            output: Vec::new(),
+            buffers: Vec::new(),
+            buffers_i16: Vec::new(),
+            output_i32: Vec::new(),
+            buffers_i32: Vec::new(),
+            buffers_f64: Vec::new(),
+            interleaved: Vec::new(),
+            mono: Vec::new(),
+            format: OutputFormat::F32,
+            stream_info,
+            seek_points: metadata.seek_points,
+            metadata_blocks: metadata.blocks,
+            replay_gain: ReplayGain::from_tags(&metadata.tags),
+            tags: metadata.tags,
+            vendor: metadata.vendor,
+            replay_gain_mode: ReplayGainMode::Off,
+            gain: 1.0,
+            playback_rate: 1.0,
+            playback_phase: 0.0,
+            soft_clip: false,
+            limiter: false,
+            dither: Dither::new(DitherMode::None),
+            fade: None,
+            peaks: Vec::new(),
+            target_sample_rate: None,
+            resampler: None,
+            dc_filter: None,
+            frame_buffer: Vec::new(),
+            pending: (0, VecDeque::new()),
+            pictures: metadata.pictures,
+            applications: metadata.applications,
+            cue_sheet: metadata.cue_sheet,
+            downmix: false,
+            downmix_matrix: None,
+            swap_channels: false,
+            invert_polarity: 0,
+            error_recovery: false,
+            crc_failures: 0,
+            stats: DecodeStats::default(),
+            follow_parameter_changes: false,
+            gapless: false,
+            keep_input: false,
+            segments: Vec::new(),
+            max_decode_per_push: None,
+            output_limit: None,
+            next_sample: 0,
+            start_sample: 0,
+            last_frame_start: 0,
+            first_frame: None,
+            pulled: 0,
+            seek_target: None,
+            verify: None,
+            buffer_capacity: capacity.unwrap_or(stream_info.max_block_size as usize),
+            ogg,
+        };
+        decoder.layout_output();
+        decoder
+    }
+
+    /// Like `new`, but runs `on_metadata` once the metadata is parsed and fails if it does.
+    pub fn with_metadata_callback<F>(buffer: Box<[u8]>, on_metadata: F) -> Result<Decoder, DecodeError>
+    where
+        F: FnOnce(&Decoder) -> Result<(), DecodeError>,
+    {
+        let decoder = Self::open(buffer, None)?;
+        on_metadata(&decoder)?;
+        Ok(decoder)
+    }
+
+    /// Like `push`, but pulls every frame as soon as it is decoded and runs `on_frame` with the
+    /// number of samples pulled, stopping at the first error it returns. The first call also
+    /// pulls whatever was still buffered.
+    pub fn push_with_frames<F>(&mut self, data: &[u8], mut on_frame: F) -> Result<usize, DecodeError>
+    where
+        F: FnMut(&Decoder, usize) -> Result<(), DecodeError>,
+    {
+        self.stats.pushes += 1;
+        self.append_input(data)?;
+        self.decode(Some(&mut on_frame))
+    }
+
+    /// Decodes the buffered input one frame at a time as the iterator is advanced, for use from
+    /// Rust without going through the pull buffers.
+    ///
+    /// Frames are read from where `push` left off and bypass the output queues, so samples
+    /// already decoded for `pull` stay there. Iteration ends at an incomplete frame, which the
+    /// next `push` completes, or at a corrupt one, which the next `push` reports.
+    pub fn frames(&mut self) -> impl Iterator<Item = Frame> + '_ {
+        std::iter::from_fn(move || self.next_frame())
+    }
+
+    /// Returns the STREAMINFO MD5 signature, or `None` when it is all zeroes (unset).
+    pub fn md5sum(&self) -> Option<&[u8; 16]> {
+        Some(&self.stream_info.md5sum).filter(|md5| md5.iter().any(|&b| b != 0))
+    }
+
+    /// Returns the Vorbis comments as `(KEY, value)` pairs in stream order.
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// Returns the parsed `REPLAYGAIN_*` tags.
+    pub fn replay_gain_tags(&self) -> &ReplayGain {
+        &self.replay_gain
+    }
+
+    pub fn decode_stats(&self) -> DecodeStats {
+        self.stats
+    }
+
+    pub fn block_headers(&self) -> &[BlockHeader] {
+        &self.metadata_blocks
+    }
+
+    pub fn pictures(&self) -> &[Picture] {
+        &self.pictures
+    }
+
+    pub fn application_blocks(&self) -> &[Application] {
+        &self.applications
+    }
+
+    pub fn front_cover(&self) -> Option<&Picture> {
+        self.pictures.iter().find(|picture| picture.picture_type == Picture::FRONT_COVER)
+    }
+
+    pub fn cue_sheet(&self) -> Option<&CueSheet> {
+        self.cue_sheet.as_ref()
+    }
+
+    pub fn seek_table(&self) -> &[SeekPoint] {
+        &self.seek_points
+    }
+
+    /// Returns a decoded sample that is still buffered for `pull`, without consuming it.
+    pub fn sample(&self, channel: usize, index: usize) -> Option<f32> {
+        self.output.get(channel).and_then(|output| output.get(index).copied())
+    }
+
+    /// Returns the samples written to `channel` by the last `pull_i16`.
+    pub fn channel_i16(&self, channel: usize) -> Option<&[i16]> {
+        self.buffers_i16.get(channel).map(Vec::as_slice)
+    }
+
+    /// Returns the samples written to `channel` by the last `pull`.
+    pub fn channel(&self, channel: usize) -> Option<&[f32]> {
+        self.buffers.get(channel).map(Vec::as_slice)
+    }
+
+    /// Returns the speaker of every output channel, as `channel_layout` does.
+    pub fn speakers(&self) -> &'static [&'static str] {
+        layout::speakers(self.output_channels())
+    }
+
+    /// Returns the peak of each output channel since the last `reset_peaks`.
+    pub fn peak_levels(&self) -> &[f32] {
+        &self.peaks
+    }
+
+    /// Returns the samples written by the last `pull_interleaved`.
+    pub fn interleaved(&self) -> &[f32] {
+        &self.interleaved
+    }
+
+    /// Returns the samples written by the last `pull_mono`.
+    pub fn mono(&self) -> &[f32] {
+        &self.mono
+    }
+
+    /// Returns the samples written to `channel` by the last `pull_i32`.
+    pub fn channel_i32(&self, channel: usize) -> Option<&[i32]> {
+        self.buffers_i32.get(channel).map(Vec::as_slice)
+    }
+
+    pub fn channel_f64(&self, channel: usize) -> Option<&[f64]> {
+        self.buffers_f64.get(channel).map(Vec::as_slice)
+    }
+
+    // Appends pushed bytes, dropping the decoded frames before them first so that streaming
+    // holds at most about a frame of input. A push without data keeps them, so a stream handed
+    // over whole stays seekable.
+    fn append_input(&mut self, data: &[u8]) -> Result<(), DecodeError> {
+        if !data.is_empty() && !self.keep_input {
+            self.drop_decoded_input();
+        }
+        match self.ogg.as_mut() {
+            Some(ogg) => self.input.extend(ogg.push(data).map_err(DecodeError::Ogg)?),
+            None => self.input.extend_from_slice(data),
+        }
+        Ok(())
+    }
+
+    fn drop_decoded_input(&mut self) {
+        self.input_start += self.input_pos as u64;
+        self.input.drain(..self.input_pos);
+        self.input_pos = 0;
+    }
+
+    fn decode(&mut self, on_frame: Option<FrameCallback>) -> Result<usize, DecodeError> {
+        let input = std::mem::take(&mut self.input);
+        let pos = Cell::new(self.input_pos as u64);
+        let result = self.decode_frames(&input, &pos, SharedCursor::new, on_frame);
+        self.input = input;
+        result
+    }
+
+    // The frame loop of `decode`, reading through the readers `open` makes so that tests can
+    // stand in for the cursor.
+    fn decode_frames<'i, R: ReadBytes>(
+        &mut self,
+        input: &'i [u8],
+        pos: &'i Cell<u64>,
+        open: fn(&'i [u8], &'i Cell<u64>) -> R,
+        mut on_frame: Option<FrameCallback>,
+    ) -> Result<usize, DecodeError> {
+        let mut reader = FrameReader::new(open(input, pos));
+        let mut total = 0;
+
+        // `input_pos` only moves past complete frames, so a partial frame at the end stays
+        // buffered and is read again from its start on the next push.
+        loop {
+            if self.max_decode_per_push.is_some_and(|max| total >= max) {
+                break Ok(total);
+            }
+            if self.gapless && input[self.input_pos..].starts_with(b"fLaC") {
+                match self.next_stream(&input[self.input_pos + 4..]) {
+                    Ok(Some(len)) => {
+                        self.input_pos += 4 + len;
+                        pos.set(self.input_pos as u64);
+                        reader = FrameReader::new(open(input, pos));
+                        continue;
+                    }
+                    Ok(None) => break Ok(total),
+                    Err(err) => break Err(err),
+                }
+            }
+            let (mut parsed, mut frame_start) = (None, None);
+            if let Parse::Header(header) = frame_header::parse(&input[self.input_pos..]) {
+                parsed = Some(header);
+                frame_start = Some(self.frame_start_sample(&header, self.input_pos));
+                if let Err(err) = self.check_parameters(&header) {
+                    break Err(err);
+                }
+                if self.is_output_full(header.block_size as usize) {
+                    break Ok(total);
+                }
+            }
+
+            match reader.read_next_or_eof(std::mem::take(&mut self.frame_buffer)) {
+                Ok(Some(_)) if pos.get() as usize == self.input_pos => {
+                    error!("Frame at byte {} consumed no input", self.input_pos);
+                    break Err(DecodeError::NoProgress);
+                }
+                Ok(Some(block)) => {
+                    self.last_frame_start = frame_start.unwrap_or(self.next_sample);
+                    if self.first_frame.is_none() {
+                        self.first_frame = parsed;
+                    }
+                    total += self.write_block(&block);
+                    self.stats.frames += 1;
+                    self.stats.samples += block.duration() as u64;
+                    self.stats.bytes += pos.get() - self.input_pos as u64;
+                    self.frame_buffer = block.into_buffer();
+                    self.input_pos = pos.get() as usize;
+                    if let Some(on_frame) = on_frame.as_mut() {
+                        let frames = self.pull(usize::MAX);
+                        if let Err(err) = on_frame(self, frames) {
+                            break Err(err);
+                        }
+                    }
+                }
+                Ok(None) => {
+                    break Ok(total);
+                }
+                Err(claxon::Error::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => {
+                    // this is ok, just break
+                    break Ok(total);
+                }
+                Err(err) if self.error_recovery => {
+                    self.count_crc_failure(&err);
+                    let from = self.input_pos;
+                    let next = frame_header::find_sync(input, from + 1, self.channels()).unwrap_or(input.len());
+                    warn!("Skipping corrupt bytes {}..{}: {}", from, next, err);
+
+                    if let Parse::Header(header) = frame_header::parse(&input[next..]) {
+                        self.next_sample = self.frame_start_sample(&header, next);
+                    }
+                    self.input_pos = next;
+                    pos.set(next as u64);
+                    reader = FrameReader::new(open(input, pos));
+                }
+                Err(err) => {
+                    self.count_crc_failure(&err);
+                    error!("Error while decoding: {:?}", &err);
+                    break Err(err.into());
+                }
+            }
+        }
+    }
+
+    fn count_crc_failure(&mut self, err: &claxon::Error) {
+        // Claxon reports checksum errors only through their message.
+        if let claxon::Error::FormatError("frame CRC mismatch" | "frame header CRC mismatch") = err {
+            self.crc_failures += 1;
+        }
+    }
+
+    // Checks the metadata of a stream appended in gapless mode, returning its length or `None`
+    // while it is incomplete.
+    fn next_stream(&mut self, data: &[u8]) -> Result<Option<usize>, DecodeError> {
+        let mut cursor = Cursor::new(data);
+        let stream_info = match read_metadata(&mut cursor) {
+            Ok(metadata) => metadata.stream_info.ok_or(DecodeError::MissingStreamInfo)?,
+            Err(claxon::Error::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let expected = self.parameters();
+        let found = StreamParameters {
+            sample_rate: stream_info.sample_rate,
+            channels: stream_info.channels,
+            bits_per_sample: stream_info.bits_per_sample,
+        };
+        if found != expected {
+            return Err(DecodeError::ParameterChange { expected, found });
+        }
+
+        debug!("Continuing gaplessly into the next stream at sample {}", self.next_sample);
+        // The MD5 signatures each cover one stream, so the concatenation cannot be verified.
+        if let Some(verify) = self.verify.as_mut() {
+            verify.contiguous = false;
+        }
+
+        // Decoding across the join again after a seek finds the stream already known.
+        let start = self.input_start + (self.input_pos + 4) as u64 + cursor.position();
+        if self.segments.last().is_none_or(|segment| start > segment.start) {
+            if self.segments.is_empty() {
+                self.segments.push(Segment {
+                    start: 0,
+                    first_sample: 0,
+                    block_size: self.stream_info.max_block_size as u32,
+                    samples: self.stream_info.samples,
+                });
+            }
+            self.segments.push(Segment {
+                start,
+                first_sample: self.next_sample,
+                block_size: stream_info.max_block_size as u32,
+                samples: stream_info.samples.filter(|&samples| samples > 0),
+            });
+        }
+        self.stream_info.max_block_size = self.stream_info.max_block_size.max(stream_info.max_block_size);
+        Ok(Some(cursor.position() as usize))
+    }
+
+    fn next_frame(&mut self) -> Option<Frame> {
+        if self.gapless && self.input[self.input_pos..].starts_with(b"fLaC") {
+            let input = std::mem::take(&mut self.input);
+            let joined = self.next_stream(&input[self.input_pos + 4..]);
+            self.input = input;
+            match joined {
+                Ok(Some(len)) => self.input_pos += 4 + len,
+                _ => return None,
+            }
+        }
+        let header = match frame_header::parse(&self.input[self.input_pos..]) {
+            Parse::Header(header) => header,
+            _ => return None,
+        };
+        let start = self.frame_start_sample(&header, self.input_pos);
+        let pos = Cell::new(self.input_pos as u64);
+        let mut reader = FrameReader::new(SharedCursor::new(&self.input, &pos));
+        let block = match reader.read_next_or_eof(Vec::new()) {
+            Ok(Some(block)) if pos.get() as usize > self.input_pos => block,
+            _ => return None,
+        };
+
+        if let Some(verify) = self.verify.as_mut() {
+            verify.update(&block, self.next_sample, self.stream_info.bits_per_sample);
+        }
+        self.next_sample += block.duration() as u64;
+        self.last_frame_start = start;
+        self.first_frame.get_or_insert(header);
+        self.stats.frames += 1;
+        self.stats.samples += block.duration() as u64;
+        self.stats.bytes += pos.get() - self.input_pos as u64;
+        self.input_pos = pos.get() as usize;
+        Some(Frame::new(block, start))
+    }
+
+    // Finds a frame at or shortly before `target` in fully buffered input and returns its first
+    // sample and position. Each step guesses a position in proportion to the target's share of
+    // the remaining samples and reads the sample number of the next frame header after it, so
+    // it converges in a few steps on evenly compressed audio. Headers that fail their CRC-8 or
+    // whose sample number does not fit between the bounds are skipped as false syncs. Where no
+    // frame is found this returns the start of the stream, which still decodes forward.
+    fn bisect(&self, target: u64) -> (u64, usize) {
+        const MAX_STEPS: usize = 64;
+
+        let fixed_block_size = self.stream_info.max_block_size as u32;
+        let channels = self.channels();
+        let mut low = (0, 0);
+        let mut high = (self.stream_samples().unwrap_or(0), self.input.len());
+        for _ in 0..MAX_STEPS {
+            let ((low_sample, low_pos), (high_sample, high_pos)) = (low, high);
+            if high_pos - low_pos <= 1 || high_sample <= low_sample || target < low_sample + fixed_block_size as u64 {
+                break;
+            }
+
+            let fraction = ((target - low_sample) as f64 / (high_sample - low_sample) as f64).min(1.0);
+            let guess = (low_pos + 1 + ((high_pos - low_pos - 1) as f64 * fraction) as usize).min(high_pos - 1);
+            let mut from = guess;
+            let found = loop {
+                let pos = match frame_header::find_sync(&self.input[..high_pos], from, channels) {
+                    Some(pos) => pos,
+                    None => break None,
+                };
+                match frame_header::parse(&self.input[pos..]) {
+                    Parse::Header(header) => {
+                        let sample = self.frame_start_sample(&header, pos);
+                        if sample > low_sample && sample < high_sample {
+                            break Some((sample, pos));
+                        }
+                    }
+                    Parse::Incomplete | Parse::Invalid => {}
+                }
+                from = pos + 1;
+            };
+
+            match found {
+                Some((sample, pos)) if sample <= target => low = (sample, pos),
+                Some((sample, pos)) => high = (sample, pos),
+                // The frame holding the target starts before the guess.
+                None => high = (high_sample, guess),
+            }
+        }
+        low
+    }
+
+    fn is_fully_buffered(&self) -> bool {
+        let total = match self.stream_samples() {
+            Some(total) => total,
+            None => return false,
+        };
+        self.input_start == 0 && self.start_sample == 0 && self.last_frame_end() == Some(total)
+    }
+
+    // Finds the last frame that decodes completely, searching back from the end of the input,
+    // and returns the sample it ends at.
+    fn last_frame_end(&self) -> Option<u64> {
+        let mut buffer = Vec::new();
+        for start in (0..self.input.len()).rev() {
+            let header = match frame_header::parse(&self.input[start..]) {
+                Parse::Header(header) if header.channels == self.channels() => header,
+                _ => continue,
+            };
+
+            let pos = Cell::new(start as u64);
+            let mut reader = FrameReader::new(SharedCursor::new(&self.input, &pos));
+            match reader.read_next_or_eof(buffer) {
+                Ok(Some(block)) => return Some(self.frame_start_sample(&header, start) + block.duration() as u64),
+                Ok(None) => return None,
+                Err(_) => buffer = Vec::new(),
+            }
+        }
+        None
+    }
+
+    // Returns the sample a frame starts at from its header at `pos` in the input, counting on
+    // across the streams joined in gapless mode.
+    fn frame_start_sample(&self, header: &FrameHeader, pos: usize) -> u64 {
+        let pos = self.input_start + pos as u64;
+        match self.segments.iter().rev().find(|segment| segment.start <= pos) {
+            Some(segment) => segment.first_sample + header.first_sample(segment.block_size),
+            None => header.first_sample(self.stream_info.max_block_size as u32),
+        }
+    }
+
+    // Returns the length of the stream through the last one joined in gapless mode, or `None`
+    // when it is unknown.
+    fn stream_samples(&self) -> Option<u64> {
+        match self.segments.last() {
+            Some(segment) => segment.samples.map(|samples| segment.first_sample + samples),
+            None => self.stream_info.samples,
+        }
+        .filter(|&total| total > 0)
+    }
+
+    // Buffers a decoded block, dropping the samples before a pending seek target.
+    fn write_block(&mut self, block: &Block) -> usize {
+        let start = self.next_sample;
+        let duration = block.duration();
+        self.next_sample += duration as u64;
+
+        if let Some(verify) = self.verify.as_mut() {
+            verify.update(block, start, self.stream_info.bits_per_sample);
+        }
+
+        let skip = match self.seek_target {
+            Some(target) if target >= self.next_sample => return 0,
+            Some(target) => {
+                self.seek_target = None;
+                target.saturating_sub(start) as u32
+            }
+            None => 0,
+        };
+        let bits = self.bit_depth();
+        let scale = self.replay_gain.scale(self.replay_gain_mode);
+        let written = self.output.first().map_or(0, VecDeque::len);
+
+        // The swap moves the inputs, except when downmixing, where it swaps the folded-down pair.
+        let downmixing = self.is_downmixing();
+        let swap = self.swap_channels && block.channels() > 1;
+        let mut channels: [&[i32]; 8] = [&[]; 8];
+        let channels = &mut channels[..block.channels() as usize];
+        for (ch, samples) in channels.iter_mut().enumerate() {
+            let source = match ch {
+                0 | 1 if swap && !downmixing => 1 - ch as u32,
+                ch => ch as u32,
+            };
+            *samples = &block.channel(source)[skip as usize..];
+        }
+        let invert = self.invert_polarity;
+        let gain = |ch: usize| if ch < 32 && invert & (1 << ch) != 0 { -scale } else { scale };
+
+        if self.is_integer_output() {
+            let shift = match self.format {
+                OutputFormat::I24 => 24 - bits as i32,
+                _ => 0,
+            };
+            IntSink { outputs: &mut self.output_i32, peaks: &mut self.peaks, invert, shift }
+                .write_frame(channels, bits);
+        } else if downmixing {
+            // A stream that changed its channel count has outgrown a custom matrix.
+            let coefficients = match self.downmix_matrix.as_deref() {
+                Some(matrix) if matrix.len() == channels.len() => matrix,
+                _ => downmix::coefficients(block.channels()),
+            };
+            let gains = [gain(0), gain(1)];
+            DownmixSink { outputs: &mut self.output, peaks: &mut self.peaks, coefficients, gains, swap }
+                .write_frame(channels, bits);
+        } else {
+            let mut gains = [scale; 8];
+            gains.iter_mut().enumerate().for_each(|(ch, g)| *g = gain(ch));
+            FloatSink { outputs: &mut self.output, peaks: &mut self.peaks, gains: &gains }
+                .write_frame(channels, bits);
+        }
+
+        if let Some(dc_filter) = self.dc_filter.as_mut() {
+            dc_filter.process(&mut self.output, written);
+        }
+        match self.resampler.as_mut() {
+            Some(resampler) => {
+                resampler.process(&mut self.output, written);
+                self.output[0].len() - written
+            }
+            None => (duration - skip) as usize,
+        }
+    }
+
+    // Whether buffering another `block_size` frames would pass the output limit.
+    fn is_output_full(&self, block_size: usize) -> bool {
+        let available = self.available_samples();
+        let frames = match self.resampler {
+            Some(_) => {
+                let resampled = block_size as u64 * self.sample_rate() as u64;
+                resampled.div_ceil(self.stream_info.sample_rate as u64) as usize + 1
+            }
+            None => block_size,
+        };
+        self.output_limit.is_some_and(|limit| available > 0 && available + frames > limit)
+    }
+
+    fn clear_output(&mut self) {
+        self.output.iter_mut().for_each(VecDeque::clear);
+        self.output_i32.iter_mut().for_each(VecDeque::clear);
+        self.pending.1.clear();
+        self.playback_phase = 0.0;
+        if let Some(resampler) = self.resampler.as_mut() {
+            resampler.reset();
+        }
+        if let Some(dc_filter) = self.dc_filter.as_mut() {
+            dc_filter.reset();
+        }
+    }
+
+    fn is_downmixing(&self) -> bool {
+        self.downmix && !self.is_integer_output() && self.channels() > 2
+    }
+
+    fn is_integer_output(&self) -> bool {
+        matches!(self.format, OutputFormat::I32 | OutputFormat::I24)
+    }
+
+    fn output_channels(&self) -> u32 {
+        if self.is_downmixing() { 2 } else { self.channels() }
+    }
+
+    fn layout_output(&mut self) {
+        let (float_channels, int_channels) = match self.format {
+            OutputFormat::I32 | OutputFormat::I24 => (0, self.channels() as usize),
+            _ => (self.output_channels() as usize, 0),
+        };
+        if self.output.len() != float_channels {
+            self.output = vec![VecDeque::new(); float_channels];
+            self.buffers = vec![Vec::with_capacity(self.buffer_capacity); float_channels];
+            self.buffers_i16 = vec![Vec::new(); float_channels];
+            self.pending.1.clear();
+        }
+        if self.output_i32.len() != int_channels {
+            self.output_i32 = vec![VecDeque::new(); int_channels];
+            self.buffers_i32 = vec![Vec::with_capacity(self.buffer_capacity); int_channels];
+        }
+        if self.buffers_f64.len() != float_channels + int_channels {
+            self.buffers_f64 = vec![Vec::new(); float_channels + int_channels];
+        }
+        if self.peaks.len() != self.output_channels() as usize {
+            self.peaks = vec![0.0; self.output_channels() as usize];
+        }
+        if self.dc_filter.is_some() {
+            self.dc_filter = Some(DcFilter::new(float_channels));
+        }
+        self.resampler = match self.target_sample_rate {
+            Some(rate) if rate != self.stream_info.sample_rate && float_channels > 0 => {
+                Some(Resampler::new(self.stream_info.sample_rate, rate, float_channels))
+            }
+            _ => None,
+        };
+    }
+
+    fn parameters(&self) -> StreamParameters {
+        StreamParameters {
+            sample_rate: self.stream_info.sample_rate,
+            channels: self.channels(),
+            bits_per_sample: self.bit_depth(),
+        }
+    }
+
+    // Compares a frame header with the stream parameters, adopting the frame's if enabled.
+    fn check_parameters(&mut self, header: &FrameHeader) -> Result<(), DecodeError> {
+        let expected = self.parameters();
+        let found = StreamParameters {
+            sample_rate: header.sample_rate.unwrap_or(expected.sample_rate),
+            channels: header.channels,
+            bits_per_sample: header.bits_per_sample.unwrap_or(expected.bits_per_sample),
+        };
+        if found == expected {
+            return Ok(());
+        }
+        if !self.follow_parameter_changes {
+            error!("Frame at byte {} changes the stream from {} to {}", self.input_pos, expected, found);
+            return Err(DecodeError::ParameterChange { expected, found });
+        }
+
+        warn!("Stream changes from {} to {}", expected, found);
+        self.stream_info.sample_rate = found.sample_rate;
+        self.stream_info.channels = found.channels;
+        self.stream_info.bits_per_sample = found.bits_per_sample;
+        self.layout_output();
+        Ok(())
+    }
+
+    // Serves one side of a stereo pair. The frames are drained from both channels at once, and
+    // the other side's samples wait in `pending` for its own call, so the queues never drift
+    // apart whatever sizes the two sides pull.
+    fn pull_channel_into(&mut self, index: usize, dest: &mut [f32]) -> usize {
+        if index >= self.output.len() {
+            return 0;
+        }
+        let other = self.output.len() - 1 - index;
+
+        let mut written = 0;
+        if self.pending.0 == index {
+            written = dest.len().min(self.pending.1.len());
+            for (dest, s) in dest.iter_mut().zip(self.pending.1.drain(..written)) {
+                *dest = s;
+            }
+        }
+        let frames = (dest.len() - written).min(self.buffered_floats());
+        if frames == 0 {
+            return written;
+        }
+
+        let gains = self.pull_gains(frames);
+        let clip = self.clip();
+        for ((dest, s), gain) in dest[written..].iter_mut().zip(self.output[index].drain(..frames)).zip(gains.iter()) {
+            *dest = apply_gain(s, gain, clip);
+        }
+        if other != index {
+            // Whatever was pending for this side has been used up, so the other side's frames
+            // can join or start its queue.
+            self.pending.0 = other;
+            let samples = self.output[other].drain(..frames).zip(gains.iter()).map(|(s, gain)| apply_gain(s, gain, clip));
+            self.pending.1.extend(samples);
+        }
+        self.pulled += frames as u64;
+        written + frames
+    }
+
+    // `pull_f32` at a playback rate other than 1.0.
+    fn pull_varispeed(&mut self, size: usize) -> usize {
+        let available = self.buffered_floats();
+        let (rate, phase) = (self.playback_rate, self.playback_phase);
+        // Past the last frame of the stream nothing follows to interpolate towards, so there
+        // that frame is read on its own instead of waiting for the next one.
+        let at_end = self.stream_samples().is_some_and(|total| self.next_sample >= total);
+        let last = available as f64 - 1.0;
+        let frames = match last - phase {
+            room if at_end && room >= 0.0 => ((room / rate).floor() as usize + 1).min(size),
+            room if room > 0.0 => ((room / rate).ceil() as usize).min(size),
+            _ => 0,
+        };
+        let gains = self.pull_gains(available);
+        let clip = self.clip();
+        for (output, buffer) in self.output.iter().zip(self.buffers.iter_mut()) {
+            buffer.clear();
+            buffer.extend((0..frames).map(|j| {
+                let position = phase + j as f64 * rate;
+                let (i, fraction) = (position as usize, position.fract() as f32);
+                let next = (i + 1).min(available - 1);
+                let a = apply_gain(output[i], gains.at(i), clip);
+                let b = apply_gain(output[next], gains.at(next), clip);
+                a + (b - a) * fraction
+            }));
+        }
+
+        // A fast rate can step past the buffered frames, the rest is skipped on the next pull.
+        // At the end of the stream the last frame is used up once the read position passes it.
+        let end = phase + frames as f64 * rate;
+        let consumed = match at_end && frames > 0 && end > last {
+            true => available,
+            false => (end as usize).min(available),
+        };
+        self.output.iter_mut().for_each(|output| drop(output.drain(..consumed)));
+        self.playback_phase = end - consumed as f64;
+        self.pulled += consumed as u64;
+        frames
+    }
+
+    // The gains for the next `frames` buffered frames of channel 0.
+    fn pull_gains(&self, frames: usize) -> Gains {
+        self.gains(self.next_sample.saturating_sub(self.buffered_floats() as u64), frames)
+    }
+
+    // The frames buffered as floats, which is none in the integer formats whatever
+    // `available_samples` says, so the float pulls drain only what they can read.
+    fn buffered_floats(&self) -> usize {
+        self.output.first().map_or(0, VecDeque::len)
+    }
+
+    fn clip(&self) -> Clip {
+        Clip { soft_clip: self.soft_clip, limiter: self.limiter }
+    }
+
+    fn gains(&self, start: u64, frames: usize) -> Gains {
+        Gains { gain: self.gain, fade: self.fade, start, total: self.total_samples(), frames }
+    }
+
+    #[cfg(feature = "wasm")]
+    fn metadata_object(&self) -> JsValue {
+        object(&[
+            ("stream_info", self.stream_info()),
+            ("tags", self.get_tags()),
+            ("pictures", self.get_pictures().into()),
+            ("has_seek_table", (!self.seek_points.is_empty()).into()),
+        ])
+    }
+
+    fn check_stereo(&self) -> Result<(), DecodeError> {
+        if self.output_channels() > 2 {
+            return Err(DecodeError::UnsupportedChannels(self.channels()));
+        }
+        Ok(())
+    }
+
+    fn no_channel(&self, index: u32) -> DecodeError {
+        DecodeError::NoChannel { index, channels: self.output_channels() }
+    }
+}
+
+impl Verify {
+    fn update(&mut self, block: &Block, start: u64, bits: u32) {
+        if !self.contiguous || start != self.next_sample {
+            self.contiguous = false;
+            return;
+        }
+        self.next_sample += block.duration() as u64;
+
+        // The signature covers interleaved little-endian samples, each in the fewest whole bytes.
+        let width = bits.div_ceil(8) as usize;
+        let mut bytes = Vec::with_capacity(block.len() as usize * width);
+        for i in 0..block.duration() {
+            for ch in 0..block.channels() {
+                bytes.extend_from_slice(&block.sample(ch, i).to_le_bytes()[..width]);
+            }
+        }
+        self.md5.update(&bytes);
+    }
+}
+
+// Stands in for a missing STREAMINFO block with what the first frame header says about the stream.
+fn stream_info_from_frame(input: &[u8]) -> Result<StreamInfo, DecodeError> {
+    let header = match frame_header::parse(input) {
+        Parse::Header(header) => header,
+        _ => return Err(DecodeError::MissingStreamInfo),
+    };
+    let (sample_rate, bits_per_sample) = match (header.sample_rate, header.bits_per_sample) {
+        (Some(sample_rate), Some(bits_per_sample)) => (sample_rate, bits_per_sample),
+        _ => return Err(DecodeError::StreamInfoRequired),
+    };
+    debug!("No STREAMINFO, using the first frame header: {:?}", header);
+
+    let block_size = header.block_size.min(u16::MAX as u32) as u16;
+    Ok(StreamInfo {
+        min_block_size: block_size,
+        max_block_size: block_size,
+        min_frame_size: None,
+        max_frame_size: None,
+        sample_rate,
+        channels: header.channels,
+        bits_per_sample,
+        samples: None,
+        md5sum: [0; 16],
+    })
+}
+
+// Finds the first frame in `input` that starts at `min_sample` or later and decodes completely,
+// and returns its offset and first sample.
+fn first_frame(input: &[u8], stream_info: &StreamInfo, min_sample: u64) -> Option<(usize, u64)> {
+    let mut from = 0;
+    let mut buffer = Vec::new();
+    while let Some(start) = frame_header::find_sync(input, from, stream_info.channels) {
+        from = start + 1;
+        let sample = match frame_header::parse(&input[start..]) {
+            Parse::Header(header) => header.first_sample(stream_info.max_block_size as u32),
+            _ => return None,
+        };
+        if sample < min_sample {
+            continue;
+        }
+
+        let pos = Cell::new(start as u64);
+        let mut reader = FrameReader::new(SharedCursor::new(input, &pos));
+        match reader.read_next_or_eof(buffer) {
+            Ok(Some(_)) => return Some((start, sample)),
+            Ok(None) => return None,
+            Err(_) => buffer = Vec::new(),
+        }
+    }
+    None
+}
+
+// Rejects parameters that would make the rest of the decoder divide by zero or shift by too much.
+fn check_stream_info(stream_info: &StreamInfo) -> Result<(), DecodeError> {
+    let fields = [
+        ("sample_rate", stream_info.sample_rate, 1..=655_350),
+        ("channels", stream_info.channels, 1..=8),
+        ("bits_per_sample", stream_info.bits_per_sample, 4..=32),
+    ];
+    match fields.iter().find(|(_, value, range)| !range.contains(value)) {
+        Some(&(field, value, _)) => Err(DecodeError::InvalidStreamInfo { field, value }),
+        None => Ok(()),
+    }
+}
+
+// The volume and fade gains of consecutive frames from `start` on.
+struct Gains {
+    gain: f32,
+    fade: Option<Fade>,
+    start: u64,
+    total: u64,
+    frames: usize,
+}
+
+impl Gains {
+    fn iter(&self) -> impl Iterator<Item = f32> + '_ {
+        (0..self.frames).map(move |i| self.at(i))
+    }
+
+    // The gain of the frame `i` frames after `start`.
+    fn at(&self, i: usize) -> f32 {
+        match self.fade {
+            Some(fade) => self.gain * fade.gain(self.start + i as u64, self.total),
+            None => self.gain,
+        }
+    }
+}
+
+// The curves that bend gained samples back towards full scale.
+#[derive(Clone, Copy)]
+struct Clip {
+    soft_clip: bool,
+    limiter: bool,
+}
+
+// Past the knee both curves bend towards ±1.0, meeting the linear part with the same slope.
+// The limiter's `x / (1 + x)` saturates like `tanh` but much more slowly, so even peaks many dB
+// over full scale still map to distinct values below 1.0 instead of rounding to a flat top.
+fn apply_gain(sample: f32, gain: f32, clip: Clip) -> f32 {
+    const KNEE: f32 = 0.9;
+
+    let mut sample = sample * gain;
+    if clip.soft_clip && sample.abs() > KNEE {
+        let excess = (sample.abs() - KNEE) / (1.0 - KNEE);
+        sample = (KNEE + (1.0 - KNEE) * excess.tanh()).copysign(sample);
+    }
+    if clip.limiter && sample.abs() > KNEE {
+        let excess = (sample.abs() - KNEE) / (1.0 - KNEE);
+        sample = (KNEE + (1.0 - KNEE) * excess / (1.0 + excess)).copysign(sample);
+    }
+    sample
+}
+
+// `noise` is in LSBs of the 16-bit output.
+fn sample_to_i16(sample: f32, noise: f32) -> i16 {
+    (sample * 32768.0 + noise).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+// Typed array constructors take the length in elements rather than bytes, so a
+// view always covers exactly the samples written by the last pull.
+#[cfg(feature = "wasm")]
+fn f32_view(samples: &[f32]) -> Result<Float32Array, DecodeError> {
+    Ok(Float32Array::new_with_byte_offset_and_length(&memory()?, samples.as_ptr() as u32, samples.len() as u32))
+}
+
+#[cfg(feature = "wasm")]
+fn i16_view(samples: &[i16]) -> Result<Int16Array, DecodeError> {
+    Ok(Int16Array::new_with_byte_offset_and_length(&memory()?, samples.as_ptr() as u32, samples.len() as u32))
+}
+
+#[cfg(feature = "wasm")]
+fn i32_view(samples: &[i32]) -> Result<Int32Array, DecodeError> {
+    Ok(Int32Array::new_with_byte_offset_and_length(&memory()?, samples.as_ptr() as u32, samples.len() as u32))
+}
+
+#[cfg(feature = "wasm")]
+fn f64_view(samples: &[f64]) -> Result<Float64Array, DecodeError> {
+    Ok(Float64Array::new_with_byte_offset_and_length(&memory()?, samples.as_ptr() as u32, samples.len() as u32))
+}
+
+// The buffer behind the module's memory, which changes whenever the memory grows.
+#[cfg(feature = "wasm")]
+fn memory() -> Result<JsValue, DecodeError> {
+    let memory = wasm_bindgen::memory().dyn_into::<WebAssembly::Memory>().map_err(|_| DecodeError::Memory)?;
+    Ok(memory.buffer())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::io::{self, Cursor};
+
+    use claxon::input::ReadBytes;
+
+    use super::Decoder;
+    use crate::common;
+
+    // Decodes frames from a cursor of its own, leaving the shared position where it started.
+    struct Stalled<'a>(Cursor<&'a [u8]>);
+
+    impl<'a> Stalled<'a> {
+        fn open(data: &'a [u8], pos: &'a Cell<u64>) -> Stalled<'a> {
+            let mut cursor = Cursor::new(data);
+            cursor.set_position(pos.get());
+            Stalled(cursor)
+        }
+    }
+
+    impl<'a> ReadBytes for Stalled<'a> {
+        fn read_u8(&mut self) -> io::Result<u8> {
+            self.0.read_u8()
+        }
+
+        fn read_u8_or_eof(&mut self) -> io::Result<Option<u8>> {
+            self.0.read_u8_or_eof()
+        }
+
+        fn read_into(&mut self, buffer: &mut [u8]) -> io::Result<()> {
+            self.0.read_into(buffer)
+        }
+
+        fn skip(&mut self, amount: u32) -> io::Result<()> {
+            self.0.skip(amount)
+        }
+    }
+
+    #[test]
+    fn fails_when_a_frame_consumes_no_input() {
+        let data = common::Flac::new(44_100, 16, vec![common::sine(4096, 50.0, 0.5, 16)]).build();
+        let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+        let input = std::mem::take(&mut decoder.input);
+        let pos = Cell::new(decoder.input_pos as u64);
+
+        let err = decoder.decode_frames(&input, &pos, Stalled::open, None).unwrap_err();
+        assert_eq!(err.code(), "no_progress");
+        assert_eq!(decoder.available_samples(), 0);
+    }
+}
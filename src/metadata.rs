@@ -0,0 +1,267 @@
+use std::convert::TryInto;
+
+use std::io::Cursor;
+
+use claxon::input::ReadBytes;
+use claxon::metadata::{read_metadata_block, MetadataBlock, StreamInfo};
+
+const SEEKTABLE: u8 = 3;
+const CUESHEET: u8 = 5;
+const PICTURE: u8 = 6;
+
+// Sample number that marks an unused seek point.
+const PLACEHOLDER: u64 = 0xffff_ffff_ffff_ffff;
+
+/// The header of a metadata block, kept for every block whether the decoder reads it or not.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockHeader {
+    /// 0 for STREAMINFO, 1 PADDING, 2 APPLICATION, 3 SEEKTABLE, 4 VORBIS_COMMENT, 5 CUESHEET, 6 PICTURE.
+    pub block_type: u8,
+    /// The length of the block in bytes, excluding this header.
+    pub length: u32,
+    pub is_last: bool,
+}
+
+/// A SEEKTABLE entry; `offset` is in bytes from the first frame header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeekPoint {
+    pub sample: u64,
+    pub offset: u64,
+    pub samples: u16,
+}
+
+/// An embedded image from a PICTURE block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Picture {
+    /// The ID3v2 APIC picture type, e.g. 3 for the front cover.
+    pub picture_type: u32,
+    pub mime_type: String,
+    pub description: String,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl Picture {
+    pub const FRONT_COVER: u32 = 3;
+}
+
+/// An APPLICATION block: a registered four-byte ID and data only that application understands.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Application {
+    pub id: [u8; 4],
+    pub data: Vec<u8>,
+}
+
+/// A CUESHEET block, describing the tracks of a single-file album rip.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CueSheet {
+    pub catalog_number: String,
+    pub lead_in: u64,
+    pub is_cd: bool,
+    /// The tracks in order, without the lead-out.
+    pub tracks: Vec<CueTrack>,
+    /// The sample offset of the lead-out, which marks the end of the last track.
+    pub lead_out: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CueTrack {
+    pub number: u8,
+    /// The first sample of the track.
+    pub offset: u64,
+    pub isrc: String,
+    pub is_audio: bool,
+    pub pre_emphasis: bool,
+    pub indices: Vec<CueIndex>,
+}
+
+/// A track index point; `offset` is in samples from the start of its track.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CueIndex {
+    pub number: u8,
+    pub offset: u64,
+}
+
+/// The metadata blocks of a stream that the decoder keeps.
+#[derive(Default)]
+pub struct Metadata {
+    pub blocks: Vec<BlockHeader>,
+    pub stream_info: Option<StreamInfo>,
+    pub seek_points: Vec<SeekPoint>,
+    /// Vorbis comments as `(KEY, value)` in stream order, keys uppercased.
+    pub tags: Vec<(String, String)>,
+    /// The vendor string of the Vorbis comment block, usually naming the encoder.
+    pub vendor: Option<String>,
+    pub pictures: Vec<Picture>,
+    pub applications: Vec<Application>,
+    pub cue_sheet: Option<CueSheet>,
+}
+
+/// Returns the length of an ID3v2 tag at the start of `data`, which some taggers wrongly prepend to FLAC.
+pub fn id3v2_len(data: &[u8]) -> Option<u64> {
+    if data.len() < 10 || &data[..3] != b"ID3" {
+        return None;
+    }
+
+    // The size is a 28-bit "synchsafe" integer that excludes the header and the optional footer.
+    let size = data[6..10].iter().fold(0u64, |size, &b| (size << 7) | (b & 0x7f) as u64);
+    let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+    Some(10 + size + footer)
+}
+
+/// Names the kind of file `data` starts with, for data that turned out not to be FLAC.
+pub fn sniff_format(data: &[u8]) -> Option<&'static str> {
+    let at = |pos: usize, magic: &[u8]| data.get(pos..pos + magic.len()) == Some(magic);
+    if at(0, b"RIFF") && at(8, b"WAVE") || at(0, b"RF64") {
+        Some("a WAV file")
+    } else if at(0, b"FORM") && (at(8, b"AIFF") || at(8, b"AIFC")) {
+        Some("an AIFF file")
+    } else if at(4, b"ftyp") {
+        Some("an MP4 or M4A file")
+    } else if at(0, b"OggS") {
+        Some("an Ogg file")
+    } else if at(0, b"ID3") {
+        Some("an MP3 file")
+    } else if data.len() >= 2 && data[0] == 0xff && data[1] & 0xfe == 0xf8 {
+        // A frame sync is only found at the start when the stream header was cut off.
+        Some("FLAC frames without the stream header, which new_raw can decode")
+    } else if data.len() >= 2 && data[0] == 0xff && data[1] & 0xe0 == 0xe0 {
+        Some("an MP3 file")
+    } else {
+        None
+    }
+}
+
+/// Reads all metadata blocks, leaving `input` at the first frame.
+///
+/// Claxon skips the blocks it does not parse itself, so those are read here.
+pub fn read_metadata<R: ReadBytes>(input: &mut R) -> claxon::Result<Metadata> {
+    let mut metadata = Metadata::default();
+    loop {
+        let header = input.read_u8()?;
+        let block_type = header & 0x7f;
+        let length = input.read_be_u24()?;
+        metadata.blocks.push(BlockHeader { block_type, length, is_last: header & 0x80 != 0 });
+
+        match block_type {
+            SEEKTABLE => {
+                let data = read_block(input, length)?;
+                metadata.seek_points = read_seek_table(&data);
+            }
+            PICTURE => {
+                let data = read_block(input, length)?;
+                metadata.pictures.push(read_picture(&data)?);
+            }
+            CUESHEET => {
+                let data = read_block(input, length)?;
+                metadata.cue_sheet = Some(read_cue_sheet(&data)?);
+            }
+            _ => match read_metadata_block(input, block_type, length)? {
+                MetadataBlock::StreamInfo(stream_info) => metadata.stream_info = Some(stream_info),
+                MetadataBlock::VorbisComment(comment) => {
+                    metadata.tags = comment.comments.iter()
+                        .map(|(comment, eq)| (comment[..*eq].to_ascii_uppercase(), comment[eq + 1..].to_string()))
+                        .collect();
+                    metadata.vendor = Some(comment.vendor);
+                }
+                MetadataBlock::Application { id, data } => {
+                    metadata.applications.push(Application { id: id.to_be_bytes(), data });
+                }
+                _ => {}
+            },
+        }
+
+        if header & 0x80 != 0 {
+            return Ok(metadata);
+        }
+    }
+}
+
+fn read_block<R: ReadBytes>(input: &mut R, length: u32) -> claxon::Result<Vec<u8>> {
+    let mut data = vec![0; length as usize];
+    input.read_into(&mut data)?;
+    Ok(data)
+}
+
+fn read_seek_table(data: &[u8]) -> Vec<SeekPoint> {
+    data.chunks_exact(18)
+        .map(|point| SeekPoint {
+            sample: u64::from_be_bytes(point[0..8].try_into().unwrap()),
+            offset: u64::from_be_bytes(point[8..16].try_into().unwrap()),
+            samples: u16::from_be_bytes(point[16..18].try_into().unwrap()),
+        })
+        .filter(|point| point.sample != PLACEHOLDER)
+        .collect()
+}
+
+fn read_picture(data: &[u8]) -> claxon::Result<Picture> {
+    let mut input = Cursor::new(data);
+    let picture_type = input.read_be_u32()?;
+    let mime_type = read_string(&mut input)?;
+    let description = read_string(&mut input)?;
+    let width = input.read_be_u32()?;
+    let height = input.read_be_u32()?;
+    // Colour depth and palette size are not interesting to a player.
+    input.skip(8)?;
+    let length = input.read_be_u32()?;
+    let data = read_block(&mut input, length)?;
+
+    Ok(Picture { picture_type, mime_type, description, width, height, data })
+}
+
+fn read_cue_sheet(data: &[u8]) -> claxon::Result<CueSheet> {
+    // Lead-out track numbers, which differ between CD-DA and other cue sheets.
+    const CD_LEAD_OUT: u8 = 170;
+    const LEAD_OUT: u8 = 255;
+
+    let mut input = Cursor::new(data);
+    let catalog_number = read_padded_string(&mut input, 128)?;
+    let lead_in = read_be_u64(&mut input)?;
+    let is_cd = input.read_u8()? & 0x80 != 0;
+    input.skip(258)?;
+
+    let mut tracks = Vec::new();
+    let mut lead_out = None;
+    for _ in 0..input.read_u8()? {
+        let offset = read_be_u64(&mut input)?;
+        let number = input.read_u8()?;
+        let isrc = read_padded_string(&mut input, 12)?;
+        let flags = input.read_u8()?;
+        input.skip(13)?;
+        let mut indices = Vec::new();
+        for _ in 0..input.read_u8()? {
+            let offset = read_be_u64(&mut input)?;
+            let number = input.read_u8()?;
+            input.skip(3)?;
+            indices.push(CueIndex { number, offset });
+        }
+
+        if number == LEAD_OUT || (is_cd && number == CD_LEAD_OUT) {
+            lead_out = Some(offset);
+        } else {
+            let (is_audio, pre_emphasis) = (flags & 0x80 == 0, flags & 0x40 != 0);
+            tracks.push(CueTrack { number, offset, isrc, is_audio, pre_emphasis, indices });
+        }
+    }
+
+    Ok(CueSheet { catalog_number, lead_in, is_cd, tracks, lead_out })
+}
+
+fn read_be_u64<R: ReadBytes>(input: &mut R) -> claxon::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_into(&mut bytes)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+// Reads a fixed-size ASCII field that is padded with NUL bytes.
+fn read_padded_string<R: ReadBytes>(input: &mut R, length: u32) -> claxon::Result<String> {
+    let mut data = read_block(input, length)?;
+    data.truncate(data.iter().position(|&b| b == 0).unwrap_or(data.len()));
+    Ok(String::from_utf8(data)?)
+}
+
+fn read_string<R: ReadBytes>(input: &mut R) -> claxon::Result<String> {
+    let length = input.read_be_u32()?;
+    Ok(String::from_utf8(read_block(input, length)?)?)
+}
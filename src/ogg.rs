@@ -0,0 +1,106 @@
+// Demuxes Ogg-encapsulated FLAC back into a native FLAC stream.
+//
+// The first packet of the logical stream carries a 9-byte Ogg mapping header
+// followed by `fLaC` and the STREAMINFO block, the next packets carry the
+// remaining metadata blocks verbatim and every packet after that is one frame.
+// Page CRCs are not checked; FLAC frames carry their own checksums.
+
+const CAPTURE_PATTERN: &[u8; 4] = b"OggS";
+const PAGE_HEADER: usize = 27;
+const MAPPING_HEADER: usize = 9;
+
+pub fn is_ogg(data: &[u8]) -> bool {
+    data.starts_with(CAPTURE_PATTERN)
+}
+
+#[derive(Default)]
+pub struct OggDemuxer {
+    pending: Vec<u8>,
+    packet: Vec<u8>,
+    serial: Option<u32>,
+    packets: u64,
+    header_packets: u64,
+}
+
+impl OggDemuxer {
+    /// Consumes Ogg pages and returns the FLAC bytes they complete; partial pages stay buffered.
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<u8>, &'static str> {
+        self.pending.extend_from_slice(data);
+
+        let mut out = Vec::new();
+        let mut pos = 0;
+        while let Some(len) = self.read_page(pos, &mut out)? {
+            pos += len;
+        }
+        self.pending.drain(..pos);
+
+        Ok(out)
+    }
+
+    fn read_page(&mut self, pos: usize, out: &mut Vec<u8>) -> Result<Option<usize>, &'static str> {
+        let page = &self.pending[pos..];
+        if page.len() < PAGE_HEADER {
+            return Ok(None);
+        }
+        if !is_ogg(page) {
+            return Err("Lost Ogg page sync");
+        }
+
+        let segments = page[26] as usize;
+        let body_start = PAGE_HEADER + segments;
+        if page.len() < body_start {
+            return Ok(None);
+        }
+        let lacing = &page[PAGE_HEADER..body_start];
+        let len = body_start + lacing.iter().map(|&l| l as usize).sum::<usize>();
+        if page.len() < len {
+            return Ok(None);
+        }
+
+        // Only the first logical stream is decoded, pages of any other are skipped.
+        let serial = u32::from_le_bytes([page[14], page[15], page[16], page[17]]);
+        if *self.serial.get_or_insert(serial) != serial {
+            return Ok(Some(len));
+        }
+
+        let mut body = body_start;
+        for i in 0..segments {
+            let lace = self.pending[pos + PAGE_HEADER + i] as usize;
+            let segment = pos + body..pos + body + lace;
+            self.packet.extend_from_slice(&self.pending[segment]);
+            body += lace;
+            if lace < 255 {
+                self.finish_packet(out)?;
+            }
+        }
+
+        Ok(Some(len))
+    }
+
+    fn finish_packet(&mut self, out: &mut Vec<u8>) -> Result<(), &'static str> {
+        let mut packet = std::mem::take(&mut self.packet);
+        self.packets += 1;
+
+        if self.packets == 1 {
+            if packet.starts_with(b"\x01vorbis") {
+                return Err("Expected FLAC, found what looks like an Ogg Vorbis file");
+            }
+            if packet.starts_with(b"OpusHead") {
+                return Err("Expected FLAC, found what looks like an Ogg Opus file");
+            }
+            if packet.len() < MAPPING_HEADER || &packet[..5] != b"\x7fFLAC" {
+                return Err("Ogg stream does not contain FLAC");
+            }
+            self.header_packets = u16::from_be_bytes([packet[7], packet[8]]) as u64;
+            out.extend_from_slice(&packet[MAPPING_HEADER..]);
+        } else {
+            // Make sure the metadata reader stops after the advertised header packets.
+            if self.packets == self.header_packets + 1 && !packet.is_empty() {
+                packet[0] |= 0x80;
+            }
+            out.extend(packet);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Which ReplayGain adjustment `push` applies to float output.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayGainMode {
+    Off,
+    Track,
+    /// Uses the album values, falling back to the track values when the album ones are missing.
+    Album,
+}
+
+/// The `REPLAYGAIN_*` Vorbis comments; gains in dB, peaks as linear amplitude.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGain {
+    pub fn from_tags(tags: &[(String, String)]) -> ReplayGain {
+        let find = |key: &str| tags.iter()
+            .find(|(name, _)| name == key)
+            .and_then(|(_, value)| parse_value(value));
+
+        ReplayGain {
+            track_gain: find("REPLAYGAIN_TRACK_GAIN"),
+            track_peak: find("REPLAYGAIN_TRACK_PEAK"),
+            album_gain: find("REPLAYGAIN_ALBUM_GAIN"),
+            album_peak: find("REPLAYGAIN_ALBUM_PEAK"),
+        }
+    }
+
+    /// Returns the linear factor for `mode`, lowered where needed so the peak does not clip.
+    pub fn scale(&self, mode: ReplayGainMode) -> f32 {
+        let (gain, peak) = match mode {
+            ReplayGainMode::Off => return 1.0,
+            ReplayGainMode::Track => (self.track_gain, self.track_peak),
+            ReplayGainMode::Album if self.album_gain.is_some() => (self.album_gain, self.album_peak),
+            ReplayGainMode::Album => (self.track_gain, self.track_peak),
+        };
+
+        let scale = gain.map_or(1.0, |gain| 10f32.powf(gain / 20.0));
+        match peak {
+            Some(peak) if peak > 0.0 => scale.min(1.0 / peak),
+            _ => scale,
+        }
+    }
+}
+
+// Accepts the usual "-6.48 dB" and "0.988553" forms, with or without the unit. Taggers that
+// went through a word processor or a locale also write a Unicode minus or dash and a decimal comma.
+fn parse_value(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let value = match value.len().checked_sub(2).and_then(|end| value.get(end..)) {
+        Some(unit) if unit.eq_ignore_ascii_case("db") => &value[..value.len() - 2],
+        _ => value,
+    };
+    let value: String = value.trim()
+        .chars()
+        .map(|c| match c {
+            '\u{2212}' | '\u{2013}' => '-',
+            ',' => '.',
+            c => c,
+        })
+        .collect();
+    value.parse().ok().filter(|value: &f32| value.is_finite())
+}
@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+
+// Converts between sample rates by linear interpolation.
+//
+// Linear interpolation is cheap and click-free but lets some aliasing through when
+// downsampling and softens the top octave slightly, which is fine for playback on devices
+// with a fixed rate but not for mastering. The fractional read position and the last input
+// sample carry over between blocks, so block boundaries are seamless.
+pub struct Resampler {
+    // Input samples advanced per output sample.
+    step: f64,
+    // Position of the next output sample, where 0.0 is `last` and 1.0 the first new input sample.
+    pos: f64,
+    last: Vec<f32>,
+    scratch: Vec<f32>,
+}
+
+impl Resampler {
+    pub fn new(from: u32, to: u32, channels: usize) -> Resampler {
+        Resampler { step: from as f64 / to as f64, pos: 1.0, last: vec![0.0; channels], scratch: Vec::new() }
+    }
+
+    /// Forgets the carried-over state, for when the input jumps.
+    pub fn reset(&mut self) {
+        self.pos = 1.0;
+        self.last.iter_mut().for_each(|last| *last = 0.0);
+    }
+
+    /// Replaces the samples from `start` on in every output with their resampled version.
+    pub fn process(&mut self, outputs: &mut [VecDeque<f32>], start: usize) {
+        let len = outputs.first().map_or(0, |output| output.len() - start);
+        if len == 0 {
+            return;
+        }
+
+        let mut pos = self.pos;
+        for (output, last) in outputs.iter_mut().zip(self.last.iter_mut()) {
+            self.scratch.clear();
+            self.scratch.extend(output.drain(start..));
+
+            let input = &self.scratch;
+            let at = |i: usize| if i == 0 { *last } else { input[i - 1] };
+            pos = self.pos;
+            while pos < len as f64 {
+                let i = pos as usize;
+                let frac = (pos - i as f64) as f32;
+                output.push_back(at(i) + (at(i + 1) - at(i)) * frac);
+                pos += self.step;
+            }
+            *last = input[len - 1];
+        }
+        self.pos = pos - len as f64;
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::VecDeque;
+
+use crate::convert::{extend_f32, sample_to_f32};
+
+/// Takes the decoded samples of one frame into the output buffers.
+///
+/// There is one implementation per buffer layout. The I16 format and interleaving are produced
+/// from the float buffers when pulling, so they need no sink of their own.
+pub trait SampleSink {
+    /// Appends a frame given as one slice of `bits`-wide samples per channel, all of equal length.
+    fn write_frame(&mut self, channels: &[&[i32]], bits: u32);
+}
+
+/// Normalized floats, one queue per channel.
+pub struct FloatSink<'a> {
+    pub outputs: &'a mut [VecDeque<f32>],
+    pub peaks: &'a mut [f32],
+    /// The gain of each channel, negated where its polarity is inverted.
+    pub gains: &'a [f32],
+}
+
+impl SampleSink for FloatSink<'_> {
+    fn write_frame(&mut self, channels: &[&[i32]], bits: u32) {
+        let outputs = self.outputs.iter_mut().zip(self.peaks.iter_mut());
+        for (((output, peak), samples), &gain) in outputs.zip(channels).zip(self.gains) {
+            extend_f32(output, samples, bits, gain, peak);
+        }
+    }
+}
+
+/// Integer samples, one queue per channel. Gain does not apply to integer output.
+pub struct IntSink<'a> {
+    pub outputs: &'a mut [VecDeque<i32>],
+    pub peaks: &'a mut [f32],
+    /// A bit per channel whose polarity is inverted.
+    pub invert: u32,
+    /// How many bits to shift the samples left, or right where negative.
+    pub shift: i32,
+}
+
+impl SampleSink for IntSink<'_> {
+    fn write_frame(&mut self, channels: &[&[i32]], bits: u32) {
+        let outputs = self.outputs.iter_mut().zip(self.peaks.iter_mut());
+        for (ch, ((output, peak), samples)) in outputs.zip(channels).enumerate() {
+            let shift = self.shift;
+            let shifted = |s: i32| if shift >= 0 { s << shift } else { s >> -shift };
+            match ch < 32 && self.invert & (1 << ch) != 0 {
+                true => output.extend(samples.iter().map(|&s| shifted(s.saturating_neg()))),
+                false => output.extend(samples.iter().map(|&s| shifted(s))),
+            }
+            *peak = samples.iter().fold(*peak, |peak, &s| peak.max(sample_to_f32(s, bits).abs()));
+        }
+    }
+}
+
+/// Normalized floats folded down to stereo.
+pub struct DownmixSink<'a> {
+    /// The left and right queues.
+    pub outputs: &'a mut [VecDeque<f32>],
+    pub peaks: &'a mut [f32],
+    /// The (left, right) gain of every input channel.
+    pub coefficients: &'a [(f32, f32)],
+    /// The gain of the left and right output, applied after swapping them.
+    pub gains: [f32; 2],
+    pub swap: bool,
+}
+
+impl SampleSink for DownmixSink<'_> {
+    fn write_frame(&mut self, channels: &[&[i32]], bits: u32) {
+        for i in 0..channels.first().map_or(0, |samples| samples.len()) {
+            let (mut l, mut r) = (0.0, 0.0);
+            for (samples, &(cl, cr)) in channels.iter().zip(self.coefficients) {
+                let s = sample_to_f32(samples[i], bits);
+                l += s * cl;
+                r += s * cr;
+            }
+            let (l, r) = if self.swap { (r, l) } else { (l, r) };
+            let (l, r) = (l * self.gains[0], r * self.gains[1]);
+            self.peaks[0] = self.peaks[0].max(l.abs());
+            self.peaks[1] = self.peaks[1].max(r.abs());
+            self.outputs[0].push_back(l);
+            self.outputs[1].push_back(r);
+        }
+    }
+}
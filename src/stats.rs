@@ -0,0 +1,12 @@
+/// Counters kept over the lifetime of a decoder, until `Decoder::reset`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecodeStats {
+    /// Frames decoded, including frames dropped by a seek.
+    pub frames: u64,
+    /// Samples per channel in those frames.
+    pub samples: u64,
+    /// Bytes of frame data decoded; bytes skipped by error recovery are not counted.
+    pub bytes: u64,
+    /// Calls to `push` and its variants.
+    pub pushes: u64,
+}
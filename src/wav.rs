@@ -0,0 +1,104 @@
+// Writes canonical PCM RIFF/WAVE files: a 16-byte fmt chunk followed by the data chunk. Files
+// with more than two channels get a WAVE_FORMAT_EXTENSIBLE fmt chunk instead, whose channel
+// mask names the speakers so players do not have to guess the layout.
+//
+// Samples go into 8, 16, 24 or 32-bit containers. Depths in between are left-justified
+// within the next container, as the format requires, and 8-bit data is unsigned.
+
+use crate::error::DecodeError;
+use crate::layout;
+
+const PCM: u16 = 1;
+const EXTENSIBLE: u16 = 0xfffe;
+// KSDATAFORMAT_SUBTYPE_PCM, the GUID that marks extensible data as integer PCM.
+const PCM_SUBTYPE: [u8; 16] = [1, 0, 0, 0, 0, 0, 0x10, 0, 0x80, 0, 0, 0xaa, 0, 0x38, 0x9b, 0x71];
+
+pub fn write(sample_rate: u32, bits: u32, channels: &[Vec<i32>]) -> Result<Vec<u8>, DecodeError> {
+    let container = container(bits);
+    let width = container / 8;
+    let frames = channels.first().map_or(0, Vec::len);
+    let block_align = channels.len() as u32 * width;
+    let extensible = channels.len() > 2;
+    let fmt_len: u64 = if extensible { 40 } else { 16 };
+    let header_len = 28 + fmt_len;
+
+    let data_len = frames as u64 * block_align as u64;
+    if header_len - 8 + data_len > u32::MAX as u64 {
+        return Err(DecodeError::WavTooLarge(data_len));
+    }
+
+    let mut out = Vec::with_capacity((header_len + data_len) as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&((header_len - 8 + data_len) as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&(fmt_len as u32).to_le_bytes());
+    out.extend_from_slice(&(if extensible { EXTENSIBLE } else { PCM }).to_le_bytes());
+    out.extend_from_slice(&(channels.len() as u16).to_le_bytes());
+    out.extend_from_slice(&sample_rate.to_le_bytes());
+    out.extend_from_slice(&(sample_rate * block_align).to_le_bytes());
+    out.extend_from_slice(&(block_align as u16).to_le_bytes());
+    out.extend_from_slice(&(container as u16).to_le_bytes());
+    if extensible {
+        out.extend_from_slice(&22u16.to_le_bytes());
+        out.extend_from_slice(&(bits as u16).to_le_bytes());
+        out.extend_from_slice(&layout::channel_mask(channels.len() as u32).to_le_bytes());
+        out.extend_from_slice(&PCM_SUBTYPE);
+    }
+
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(data_len as u32).to_le_bytes());
+
+    write_samples(&mut out, bits, channels);
+    Ok(out)
+}
+
+/// Returns the samples interleaved in the containers of a WAV file, without the header.
+pub fn pcm(bits: u32, channels: &[Vec<i32>]) -> Vec<u8> {
+    let container = container(bits);
+    let frames = channels.first().map_or(0, Vec::len);
+    let mut out = Vec::with_capacity(frames * channels.len() * container as usize / 8);
+    write_samples(&mut out, bits, channels);
+    out
+}
+
+fn container(bits: u32) -> u32 {
+    match bits {
+        0..=8 => 8,
+        9..=16 => 16,
+        17..=24 => 24,
+        _ => 32,
+    }
+}
+
+fn write_samples(out: &mut Vec<u8>, bits: u32, channels: &[Vec<i32>]) {
+    let container = container(bits);
+    let shift = container - bits;
+    let width = container as usize / 8;
+    for i in 0..channels.first().map_or(0, Vec::len) {
+        for channel in channels {
+            let sample = channel[i] << shift;
+            if container == 8 {
+                out.push((sample + 128) as u8);
+            } else {
+                out.extend_from_slice(&sample.to_le_bytes()[..width]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // Claxon cannot decode 32-bit frames, so this is written from integers directly.
+    #[test]
+    fn writes_32_bit_samples_unchanged() {
+        let channels = [vec![i32::MAX, i32::MIN, 0x1234_5679, -0x1234_5679, 1, -1]];
+        let wav = super::write(96_000, 32, &channels).unwrap();
+        assert_eq!(u16::from_le_bytes([wav[34], wav[35]]), 32);
+
+        let expected: Vec<u8> = channels[0].iter().flat_map(|s| s.to_le_bytes()).collect();
+        assert_eq!(&wav[44..], &expected[..]);
+        assert_eq!(super::pcm(32, &channels), expected);
+    }
+}
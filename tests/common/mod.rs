@@ -0,0 +1,445 @@
+//! A tiny FLAC encoder used to build test fixtures in memory.
+//!
+//! Frames are written with verbatim subframes only, which keeps the encoder
+//! small while still producing streams any conforming decoder accepts.
+
+#![allow(dead_code)]
+
+pub const STREAMINFO: u8 = 0;
+pub const PADDING: u8 = 1;
+pub const APPLICATION: u8 = 2;
+pub const SEEKTABLE: u8 = 3;
+pub const VORBIS_COMMENT: u8 = 4;
+pub const CUESHEET: u8 = 5;
+pub const PICTURE: u8 = 6;
+
+pub struct Flac {
+    sample_rate: u32,
+    bits: u32,
+    channels: Vec<Vec<i32>>,
+    block_sizes: Vec<usize>,
+    variable: bool,
+    streaminfo: bool,
+    total_samples: Option<u64>,
+    md5: [u8; 16],
+    blocks: Vec<(u8, Vec<u8>)>,
+}
+
+impl Flac {
+    pub fn new(sample_rate: u32, bits: u32, channels: Vec<Vec<i32>>) -> Flac {
+        Flac {
+            sample_rate,
+            bits,
+            channels,
+            block_sizes: vec![4096],
+            variable: false,
+            streaminfo: true,
+            total_samples: None,
+            md5: [0; 16],
+            blocks: Vec::new(),
+        }
+    }
+
+    pub fn block_size(mut self, size: usize) -> Flac {
+        self.block_sizes = vec![size];
+        self.variable = false;
+        self
+    }
+
+    /// Cycles through the given block sizes and marks the stream as variable-blocksize.
+    pub fn variable_block_sizes(mut self, sizes: &[usize]) -> Flac {
+        self.block_sizes = sizes.to_vec();
+        self.variable = true;
+        self
+    }
+
+    pub fn without_streaminfo(mut self) -> Flac {
+        self.streaminfo = false;
+        self
+    }
+
+    /// Overrides the total sample count written to STREAMINFO (0 means unknown).
+    pub fn total_samples(mut self, samples: u64) -> Flac {
+        self.total_samples = Some(samples);
+        self
+    }
+
+    pub fn md5(mut self, md5: [u8; 16]) -> Flac {
+        self.md5 = md5;
+        self
+    }
+
+    pub fn metadata(mut self, block_type: u8, data: Vec<u8>) -> Flac {
+        self.blocks.push((block_type, data));
+        self
+    }
+
+    /// Returns the byte offset of every frame relative to the first frame.
+    pub fn frame_offsets(&self) -> Vec<u64> {
+        let mut offset = 0;
+        self.frames()
+            .iter()
+            .map(|frame| {
+                let start = offset;
+                offset += frame.len() as u64;
+                start
+            })
+            .collect()
+    }
+
+    /// Adds a SEEKTABLE pointing at every `every`-th frame of a fixed-blocksize stream.
+    pub fn with_seektable(self, every: usize) -> Flac {
+        let block_size = self.block_sizes[0] as u64;
+        let points: Vec<_> = self.frame_offsets()
+            .into_iter()
+            .enumerate()
+            .step_by(every)
+            .map(|(i, offset)| (i as u64 * block_size, offset, block_size as u16))
+            .collect();
+        self.metadata(SEEKTABLE, seektable(&points))
+    }
+
+    /// Returns the `fLaC` magic and all metadata blocks.
+    pub fn header(&self) -> Vec<u8> {
+        let mut out = b"fLaC".to_vec();
+        let mut blocks = Vec::new();
+        if self.streaminfo {
+            blocks.push((STREAMINFO, self.streaminfo_bytes()));
+        }
+        blocks.extend(self.blocks.iter().cloned());
+
+        let count = blocks.len();
+        for (i, (block_type, data)) in blocks.into_iter().enumerate() {
+            let last = if i + 1 == count { 0x80 } else { 0 };
+            out.push(last | block_type);
+            out.extend_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+            out.extend(data);
+        }
+        out
+    }
+
+    pub fn streaminfo_bytes(&self) -> Vec<u8> {
+        let frames = self.frames();
+        let min_frame = frames.iter().map(Vec::len).min().unwrap_or(0) as u64;
+        let max_frame = frames.iter().map(Vec::len).max().unwrap_or(0) as u64;
+        let min_block = *self.block_sizes.iter().min().unwrap() as u64;
+        let max_block = *self.block_sizes.iter().max().unwrap() as u64;
+        let samples = self.total_samples.unwrap_or(self.len() as u64);
+
+        let mut w = BitWriter::default();
+        w.write(min_block, 16);
+        w.write(max_block, 16);
+        w.write(min_frame, 24);
+        w.write(max_frame, 24);
+        w.write(self.sample_rate as u64, 20);
+        w.write(self.channels.len() as u64 - 1, 3);
+        w.write(self.bits as u64 - 1, 5);
+        w.write(samples, 36);
+        let mut out = w.finish();
+        out.extend_from_slice(&self.md5);
+        out
+    }
+
+    /// Returns every encoded frame separately.
+    pub fn frames(&self) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        let mut start = 0;
+        let mut index = 0;
+        while start < self.len() {
+            let size = self.block_sizes[index % self.block_sizes.len()].min(self.len() - start);
+            let number = if self.variable { start as u64 } else { index as u64 };
+            frames.push(self.frame(start, size, number));
+            start += size;
+            index += 1;
+        }
+        frames
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut out = self.header();
+        for frame in self.frames() {
+            out.extend(frame);
+        }
+        out
+    }
+
+    fn len(&self) -> usize {
+        self.channels[0].len()
+    }
+
+    fn frame(&self, start: usize, size: usize, number: u64) -> Vec<u8> {
+        let mut header = vec![0xff, if self.variable { 0xf9 } else { 0xf8 }];
+
+        let (rate_code, rate_tail) = match self.sample_rate {
+            88_200 => (0b0001, vec![]),
+            176_400 => (0b0010, vec![]),
+            192_000 => (0b0011, vec![]),
+            8_000 => (0b0100, vec![]),
+            16_000 => (0b0101, vec![]),
+            22_050 => (0b0110, vec![]),
+            24_000 => (0b0111, vec![]),
+            32_000 => (0b1000, vec![]),
+            44_100 => (0b1001, vec![]),
+            48_000 => (0b1010, vec![]),
+            96_000 => (0b1011, vec![]),
+            n if n <= 0xffff => (0b1101, (n as u16).to_be_bytes().to_vec()),
+            n => (0b1110, ((n / 10) as u16).to_be_bytes().to_vec()),
+        };
+        header.push(0b0111_0000 | rate_code);
+
+        let bits_code = match self.bits {
+            8 => 0b001,
+            12 => 0b010,
+            16 => 0b100,
+            20 => 0b101,
+            24 => 0b110,
+            _ => 0b000,
+        };
+        header.push(((self.channels.len() as u8 - 1) << 4) | (bits_code << 1));
+        header.extend(utf8_number(number));
+        header.extend_from_slice(&(size as u16 - 1).to_be_bytes());
+        header.extend(rate_tail);
+        header.push(crc8(&header));
+
+        let mut w = BitWriter::default();
+        for channel in &self.channels {
+            // Subframe header: zero pad bit, verbatim type, no wasted bits.
+            w.write(0b0000_0010, 8);
+            for &sample in &channel[start..start + size] {
+                w.write(sample as u32 as u64 & mask(self.bits), self.bits);
+            }
+        }
+
+        let mut frame = header;
+        frame.extend(w.finish());
+        let crc = crc16(&frame);
+        frame.extend_from_slice(&crc.to_be_bytes());
+        frame
+    }
+}
+
+/// Wraps a stream in Ogg pages following the Ogg FLAC mapping.
+pub fn ogg(flac: &Flac) -> Vec<u8> {
+    let header = flac.header();
+    let mut blocks = Vec::new();
+    let mut pos = 4;
+    while pos < header.len() {
+        let len = u32::from_be_bytes([0, header[pos + 1], header[pos + 2], header[pos + 3]]) as usize;
+        blocks.push(header[pos..pos + 4 + len].to_vec());
+        pos += 4 + len;
+    }
+
+    let mut first = b"\x7fFLAC\x01\x00".to_vec();
+    first.extend_from_slice(&(blocks.len() as u16 - 1).to_be_bytes());
+    first.extend_from_slice(b"fLaC");
+    first.extend(blocks.remove(0));
+
+    let mut out = Vec::new();
+    let mut sequence = 0;
+    let mut page = |packet: &[u8], flags: u8| {
+        let mut out_page = Vec::new();
+        let mut lacing = vec![255u8; packet.len() / 255];
+        lacing.push((packet.len() % 255) as u8);
+        for (i, chunk) in lacing.chunks(255).enumerate() {
+            let start: usize = lacing[..i * 255].iter().map(|&l| l as usize).sum();
+            let len: usize = chunk.iter().map(|&l| l as usize).sum();
+            let mut p = b"OggS\x00".to_vec();
+            p.push(flags | if i > 0 { 0x01 } else { 0 });
+            p.extend_from_slice(&0u64.to_le_bytes());
+            p.extend_from_slice(&0x464c_4143u32.to_le_bytes());
+            p.extend_from_slice(&(sequence as u32).to_le_bytes());
+            p.extend_from_slice(&[0; 4]);
+            p.push(chunk.len() as u8);
+            p.extend_from_slice(chunk);
+            p.extend_from_slice(&packet[start..start + len]);
+            let crc = ogg_crc(&p);
+            p[22..26].copy_from_slice(&crc.to_le_bytes());
+            out_page.extend(p);
+            sequence += 1;
+        }
+        out_page
+    };
+
+    out.extend(page(&first, 0x02));
+    for block in blocks {
+        out.extend(page(&block, 0));
+    }
+    for frame in flac.frames() {
+        out.extend(page(&frame, 0));
+    }
+    out
+}
+
+fn ogg_crc(data: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for &byte in data {
+        crc ^= (byte as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04c1_1db7 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// Builds a SEEKTABLE payload from `(sample, offset, frame_samples)` triples.
+pub fn seektable(points: &[(u64, u64, u16)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for &(sample, offset, samples) in points {
+        out.extend_from_slice(&sample.to_be_bytes());
+        out.extend_from_slice(&offset.to_be_bytes());
+        out.extend_from_slice(&samples.to_be_bytes());
+    }
+    out
+}
+
+/// Builds a VORBIS_COMMENT payload.
+pub fn vorbis_comment(vendor: &str, comments: &[&str]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    out.extend_from_slice(vendor.as_bytes());
+    out.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        out.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        out.extend_from_slice(comment.as_bytes());
+    }
+    out
+}
+
+/// Builds a PICTURE payload.
+pub fn picture(picture_type: u32, mime: &str, description: &str, width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&picture_type.to_be_bytes());
+    out.extend_from_slice(&(mime.len() as u32).to_be_bytes());
+    out.extend_from_slice(mime.as_bytes());
+    out.extend_from_slice(&(description.len() as u32).to_be_bytes());
+    out.extend_from_slice(description.as_bytes());
+    out.extend_from_slice(&width.to_be_bytes());
+    out.extend_from_slice(&height.to_be_bytes());
+    out.extend_from_slice(&24u32.to_be_bytes());
+    out.extend_from_slice(&0u32.to_be_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+/// Builds an APPLICATION payload.
+pub fn application(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut out = id.to_vec();
+    out.extend_from_slice(data);
+    out
+}
+
+/// A cue sheet track: `(offset, number, isrc, indices as (offset, number))`.
+pub type CueTrack<'a> = (u64, u8, &'a str, &'a [(u64, u8)]);
+
+/// Builds a CUESHEET payload.
+pub fn cuesheet(lead_in: u64, is_cd: bool, tracks: &[CueTrack]) -> Vec<u8> {
+    let mut out = vec![0u8; 128];
+    out.extend_from_slice(&lead_in.to_be_bytes());
+    out.push(if is_cd { 0x80 } else { 0 });
+    out.extend_from_slice(&[0u8; 258]);
+    out.push(tracks.len() as u8);
+    for &(offset, number, isrc, indices) in tracks {
+        out.extend_from_slice(&offset.to_be_bytes());
+        out.push(number);
+        let mut isrc_bytes = [0u8; 12];
+        isrc_bytes[..isrc.len()].copy_from_slice(isrc.as_bytes());
+        out.extend_from_slice(&isrc_bytes);
+        out.push(0);
+        out.extend_from_slice(&[0u8; 13]);
+        out.push(indices.len() as u8);
+        for &(index_offset, index_number) in indices {
+            out.extend_from_slice(&index_offset.to_be_bytes());
+            out.push(index_number);
+            out.extend_from_slice(&[0u8; 3]);
+        }
+    }
+    out
+}
+
+/// A deterministic test tone in the given bit depth.
+pub fn sine(len: usize, period: f64, amplitude: f64, bits: u32) -> Vec<i32> {
+    let full_scale = ((1i64 << (bits - 1)) - 1) as f64;
+    (0..len)
+        .map(|i| ((i as f64 * std::f64::consts::TAU / period).sin() * amplitude * full_scale).round() as i32)
+        .collect()
+}
+
+/// A sawtooth that steps through the full range of the given bit depth.
+pub fn ramp(len: usize, bits: u32) -> Vec<i32> {
+    let min = -(1i64 << (bits - 1));
+    let span = 1i64 << bits;
+    (0..len as i64).map(|i| (min + (i * 997) % span) as i32).collect()
+}
+
+fn mask(bits: u32) -> u64 {
+    (1u64 << bits) - 1
+}
+
+fn utf8_number(n: u64) -> Vec<u8> {
+    if n < 0x80 {
+        return vec![n as u8];
+    }
+    let mut len = 2;
+    while n >= 1u64 << (5 * len + 1) {
+        len += 1;
+    }
+    let mut out = vec![0u8; len];
+    let mut rest = n;
+    for byte in out.iter_mut().skip(1).rev() {
+        *byte = 0x80 | (rest & 0x3f) as u8;
+        rest >>= 6;
+    }
+    out[0] = (0xff00u16 >> len) as u8 | rest as u8;
+    out
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x8005 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    len: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, bits: u32) {
+        for i in (0..bits).rev() {
+            self.acc = (self.acc << 1) | ((value >> i) & 1);
+            self.len += 1;
+            if self.len == 8 {
+                self.bytes.push(self.acc as u8);
+                self.acc = 0;
+                self.len = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push((self.acc << (8 - self.len)) as u8);
+        }
+        self.bytes
+    }
+}
@@ -0,0 +1,1065 @@
+mod common;
+
+use common::{ramp, Flac};
+use flac_decoder::{DecodeStats, Decoder, DitherMode, Frame, OutputFormat};
+
+#[test]
+fn decodes_mono() {
+    let samples = vec![0, 16384, -16384, 32767, -32768, 1];
+    let data = Flac::new(44_100, 16, vec![samples]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert_eq!(decoder.channels(), 1);
+    assert_eq!(decoder.push(&[]).unwrap(), 6);
+
+    let expected = [0.0, 0.5, -0.5, 32767.0 / 32768.0, -1.0, 1.0 / 32768.0];
+    for (i, &value) in expected.iter().enumerate() {
+        assert_eq!(decoder.sample(0, i), Some(value));
+    }
+    assert_eq!(decoder.sample(1, 0), None);
+}
+
+#[test]
+fn decodes_stereo() {
+    let left = ramp(10_000, 16);
+    let right: Vec<i32> = left.iter().map(|s| -s - 1).collect();
+    let data = Flac::new(48_000, 16, vec![left.clone(), right.clone()]).block_size(1152).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert_eq!(decoder.push(&[]).unwrap(), 10_000);
+    for i in [0, 1, 1151, 1152, 9999] {
+        assert_eq!(decoder.sample(0, i), Some(left[i] as f32 / 32768.0));
+        assert_eq!(decoder.sample(1, i), Some(right[i] as f32 / 32768.0));
+    }
+}
+
+#[test]
+fn decodes_surround_channels_separately() {
+    let channels: Vec<Vec<i32>> = (0..6).map(|ch| vec![ch * 1000; 300]).collect();
+    let data = Flac::new(48_000, 16, channels).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert_eq!(decoder.channels(), 6);
+    assert_eq!(decoder.push(&[]).unwrap(), 300);
+    for ch in 0..6 {
+        assert_eq!(decoder.sample(ch, 299), Some((ch * 1000) as f32 / 32768.0));
+    }
+}
+
+#[test]
+fn downmixes_surround_to_stereo() {
+    // FL FR FC LFE BL BR
+    let levels = [8192, 4096, 16384, 32767, 2048, 1024];
+    let channels: Vec<Vec<i32>> = levels.iter().map(|&level| vec![level; 64]).collect();
+    let data = Flac::new(48_000, 16, channels).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.set_downmix(true);
+    assert_eq!(decoder.push(&[]).unwrap(), 64);
+
+    let f = |level: i32| level as f32 / 32768.0;
+    let c = std::f32::consts::FRAC_1_SQRT_2;
+    let left = f(8192) + c * f(16384) + c * f(2048);
+    let right = f(4096) + c * f(16384) + c * f(1024);
+    assert!((decoder.sample(0, 63).unwrap() - left).abs() < 1e-6);
+    assert!((decoder.sample(1, 63).unwrap() - right).abs() < 1e-6);
+    assert_eq!(decoder.sample(2, 0), None);
+}
+
+#[test]
+fn downmixes_with_a_custom_matrix() {
+    let channels = vec![vec![1000; 64], vec![2000; 64], vec![3000; 64]];
+    let data = Flac::new(48_000, 16, channels).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.set_downmix(true);
+    let err = decoder.set_downmix_matrix(&[1.0, 0.0, 0.0, 1.0]).err();
+    assert_eq!(err.map(|e| e.code()), Some("downmix_matrix"));
+    // The centre goes left, the front left goes right and the front right is dropped.
+    decoder.set_downmix_matrix(&[0.0, 0.0, 1.0, 1.0, 0.0, 0.0]).unwrap();
+    assert_eq!(decoder.push(&[]).unwrap(), 64);
+    assert_eq!(decoder.sample(0, 63), Some(3000.0 / 32768.0));
+    assert_eq!(decoder.sample(1, 63), Some(1000.0 / 32768.0));
+}
+
+#[test]
+fn pulls_16_bit_samples() {
+    let left = ramp(5000, 16);
+    let right = common::sine(5000, 64.0, 1.0, 16);
+    let data = Flac::new(44_100, 16, vec![left.clone(), right.clone()]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.push(&[]).unwrap();
+    assert_eq!(decoder.pull_i16(4096), 4096);
+
+    let expected = |samples: &[i32]| samples[..4096].iter().map(|&s| s as i16).collect::<Vec<_>>();
+    assert_eq!(decoder.channel_i16(0).unwrap(), expected(&left).as_slice());
+    assert_eq!(decoder.channel_i16(1).unwrap(), expected(&right).as_slice());
+}
+
+#[test]
+fn keeps_raw_integers_in_i32_mode() {
+    let left = ramp(3000, 24);
+    let right: Vec<i32> = left.iter().rev().cloned().collect();
+    let data = Flac::new(96_000, 24, vec![left.clone(), right.clone()]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.set_output_format(OutputFormat::I32);
+    decoder.push(&[]).unwrap();
+    assert_eq!(decoder.pull(3000), 3000);
+    assert_eq!(decoder.channel_i32(0).unwrap(), left.as_slice());
+    assert_eq!(decoder.channel_i32(1).unwrap(), right.as_slice());
+    assert_eq!(decoder.sample(0, 0), None);
+}
+
+#[test]
+fn pulls_24_bit_integers_at_any_depth() {
+    let samples: Vec<i32> = (0..3000).map(|i| (i * 2797 % 16_777_216) - 8_388_608).collect();
+    let data = Flac::new(96_000, 24, vec![samples.clone()]).build();
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.set_output_format(OutputFormat::I24);
+    decoder.push(&[]).unwrap();
+    assert_eq!(decoder.pull(3000), 3000);
+    assert_eq!(decoder.channel_i32(0).unwrap(), samples.as_slice());
+
+    let samples = ramp(1000, 16);
+    let data = Flac::new(44_100, 16, vec![samples.clone()]).build();
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.set_output_format(OutputFormat::I24);
+    decoder.push(&[]).unwrap();
+    assert_eq!(decoder.pull(1000), 1000);
+    let expected: Vec<i32> = samples.iter().map(|s| s * 256).collect();
+    assert_eq!(decoder.channel_i32(0).unwrap(), expected.as_slice());
+}
+
+#[test]
+fn pulls_doubles_without_rounding_through_floats() {
+    let left = ramp(3000, 24);
+    let right: Vec<i32> = left.iter().map(|s| (s / 3) | 1).collect();
+    let data = Flac::new(96_000, 24, vec![left.clone(), right.clone()]).build();
+
+    let mut decoder = Decoder::new(data.clone().into_boxed_slice()).unwrap();
+    decoder.set_output_format(OutputFormat::I32);
+    decoder.push(&[]).unwrap();
+    assert_eq!(decoder.pull_f64(3000), 3000);
+    let reference = |samples: &[i32]| -> Vec<f64> { samples.iter().map(|&s| s as f64 / 8_388_608.0).collect() };
+    assert_eq!(decoder.channel_f64(0).unwrap(), reference(&left).as_slice());
+    assert_eq!(decoder.channel_f64(1).unwrap(), reference(&right).as_slice());
+
+    // The float formats are exact only to f32, which a 3 dB gain shows.
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.set_gain(std::f32::consts::SQRT_2);
+    decoder.push(&[]).unwrap();
+    assert_eq!(decoder.pull_f64(3000), 3000);
+    let error = decoder.channel_f64(1).unwrap().iter().zip(reference(&right))
+        .map(|(&s, r)| (s - r * std::f64::consts::SQRT_2).abs())
+        .fold(0.0, f64::max);
+    assert!(error > 1e-12 && error < 1e-7, "{}", error);
+}
+
+#[test]
+fn pulls_interleaved_frames() {
+    let left = ramp(100, 16);
+    let right: Vec<i32> = left.iter().map(|s| s / 2).collect();
+    let data = Flac::new(44_100, 16, vec![left.clone(), right.clone()]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.push(&[]).unwrap();
+    assert_eq!(decoder.pull_interleaved(64), 64);
+
+    let interleaved = decoder.interleaved();
+    assert_eq!(interleaved.len(), 128);
+    for i in 0..64 {
+        assert_eq!(interleaved[2 * i], left[i] as f32 / 32768.0);
+        assert_eq!(interleaved[2 * i + 1], right[i] as f32 / 32768.0);
+    }
+
+    assert_eq!(decoder.pull_interleaved(1000), 36);
+    assert_eq!(decoder.interleaved().len(), 72);
+}
+
+#[test]
+fn pulls_a_mono_mixdown() {
+    let left = ramp(3000, 16);
+    let right: Vec<i32> = left.iter().map(|s| -s / 3).collect();
+    let data = Flac::new(44_100, 16, vec![left.clone(), right.clone()]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.push(&[]).unwrap();
+    assert_eq!(decoder.pull_mono(1000), 1000);
+    assert_eq!(decoder.available_samples(), 2000);
+    for (i, &mono) in decoder.mono().iter().enumerate() {
+        let average = (left[i] as f32 / 32768.0 + right[i] as f32 / 32768.0) / 2.0;
+        assert!((mono - average).abs() < 1e-6);
+    }
+    decoder.pull(1);
+    assert_eq!(decoder.channel(0).unwrap()[0], left[1000] as f32 / 32768.0);
+}
+
+#[test]
+fn swaps_channels_and_inverts_polarity() {
+    let left = ramp(500, 16);
+    let right: Vec<i32> = left.iter().map(|s| s / 2).collect();
+    let data = Flac::new(44_100, 16, vec![left.clone(), right.clone()]).build();
+    let decoded = |swap, mask| {
+        let mut decoder = Decoder::new(data.clone().into_boxed_slice()).unwrap();
+        decoder.set_swap_channels(swap);
+        decoder.set_invert_polarity(mask);
+        decoder.push(&[]).unwrap();
+        decoder.pull(500);
+        (decoder.channel(0).unwrap().to_vec(), decoder.channel(1).unwrap().to_vec())
+    };
+    let float = |samples: &[i32]| -> Vec<f32> { samples.iter().map(|&s| s as f32 / 32768.0).collect() };
+
+    assert_eq!(decoded(true, 0), (float(&right), float(&left)));
+    let (l, r) = decoded(false, 1);
+    assert_eq!(l, left.iter().map(|&s| -s as f32 / 32768.0).collect::<Vec<_>>());
+    assert_eq!(r, float(&right));
+    let (l, r) = decoded(true, 2);
+    assert_eq!(l, float(&right));
+    assert_eq!(r, left.iter().map(|&s| -s as f32 / 32768.0).collect::<Vec<_>>());
+}
+
+#[test]
+fn every_output_format_decodes_the_same_samples() {
+    let channels = vec![ramp(2500, 16), common::sine(2500, 100.0, 0.5, 16), vec![-1234; 2500]];
+    let data = Flac::new(44_100, 16, channels.clone()).build();
+    let decoded = |format| {
+        let mut decoder = Decoder::new(data.clone().into_boxed_slice()).unwrap();
+        decoder.set_output_format(format);
+        decoder.set_invert_polarity(0b100);
+        decoder.push(&[]).unwrap();
+        assert_eq!(decoder.pull(2500), 2500);
+        decoder
+    };
+
+    let (f32s, i16s, i32s) = (decoded(OutputFormat::F32), decoded(OutputFormat::I16), decoded(OutputFormat::I32));
+    for (ch, samples) in channels.iter().enumerate() {
+        let sign = if ch == 2 { -1 } else { 1 };
+        let expected: Vec<i32> = samples.iter().map(|s| s * sign).collect();
+        assert_eq!(i32s.channel_i32(ch).unwrap(), expected.as_slice());
+        let floats: Vec<f32> = expected.iter().map(|&s| s as f32 / 32768.0).collect();
+        assert_eq!(f32s.channel(ch).unwrap(), floats.as_slice());
+        let shorts: Vec<i16> = expected.iter().map(|&s| s as i16).collect();
+        assert_eq!(i16s.channel_i16(ch).unwrap(), shorts.as_slice());
+    }
+    assert_eq!(f32s.peak_levels()[2], 1234.0 / 32768.0);
+    assert_eq!(i32s.peak_levels()[2], 1234.0 / 32768.0);
+}
+
+#[test]
+fn float_pulls_leave_integer_output_alone() {
+    let samples = ramp(2500, 16);
+    let data = Flac::new(44_100, 16, vec![samples.clone(), samples.clone()]).build();
+    type Pull = fn(&mut Decoder) -> usize;
+    let pulls: [(&str, Pull); 5] = [
+        ("pull_f32", |decoder| decoder.pull_f32(1000)),
+        ("pull_i16", |decoder| decoder.pull_i16(1000)),
+        ("pull_interleaved", |decoder| decoder.pull_interleaved(1000)),
+        ("pull_mono", |decoder| decoder.pull_mono(1000)),
+        ("pull_at", |decoder| decoder.pull_at(0, 1000)),
+    ];
+
+    for (name, pull) in pulls {
+        let mut decoder = Decoder::new(data.clone().into_boxed_slice()).unwrap();
+        decoder.set_output_format(OutputFormat::I32);
+        decoder.push(&[]).unwrap();
+        assert_eq!(pull(&mut decoder), 0, "{}", name);
+        assert_eq!(decoder.pulled_samples(), 0, "{}", name);
+        assert_eq!(decoder.available_samples(), 2500, "{}", name);
+        assert_eq!(decoder.pull_i32(2500), 2500, "{}", name);
+        assert_eq!(decoder.channel_i32(1).unwrap(), samples.as_slice(), "{}", name);
+    }
+}
+
+#[test]
+fn swaps_and_inverts_the_downmixed_pair() {
+    let levels = [8192, 4096, 16384, 0, 2048, 1024];
+    let channels: Vec<Vec<i32>> = levels.iter().map(|&level| vec![level; 64]).collect();
+    let data = Flac::new(48_000, 16, channels).build();
+    let downmixed = |swap, mask| {
+        let mut decoder = Decoder::new(data.clone().into_boxed_slice()).unwrap();
+        decoder.set_downmix(true);
+        decoder.set_swap_channels(swap);
+        decoder.set_invert_polarity(mask);
+        decoder.push(&[]).unwrap();
+        (decoder.sample(0, 10).unwrap(), decoder.sample(1, 10).unwrap())
+    };
+
+    let (left, right) = downmixed(false, 0);
+    assert_eq!(downmixed(true, 0), (right, left));
+    assert_eq!(downmixed(true, 1), (-right, left));
+    assert_eq!(downmixed(false, 2), (left, -right));
+}
+
+#[test]
+fn pull_consumes_buffered_frames() {
+    let samples = ramp(10_000, 16);
+    let data = Flac::new(44_100, 16, vec![samples.clone()]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    let decoded = decoder.push(&[]).unwrap();
+
+    let mut pulled = Vec::new();
+    loop {
+        let frames = decoder.pull_interleaved(1024);
+        if frames == 0 {
+            break;
+        }
+        pulled.extend_from_slice(decoder.interleaved());
+    }
+
+    assert_eq!(pulled.len(), decoded);
+    let expected: Vec<f32> = samples.iter().map(|&s| s as f32 / 32768.0).collect();
+    assert_eq!(pulled, expected);
+}
+
+#[test]
+fn peeks_ahead_without_consuming() {
+    let left = ramp(3000, 16);
+    let right: Vec<i32> = left.iter().map(|s| -s).collect();
+    let data = Flac::new(44_100, 16, vec![left.clone(), right.clone()]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.push(&[]).unwrap();
+    assert_eq!(decoder.pull_at(1000, 500), 500);
+    assert_eq!(decoder.available_samples(), 3000);
+    let float = |samples: &[i32]| -> Vec<f32> { samples.iter().map(|&s| s as f32 / 32768.0).collect() };
+    assert_eq!(decoder.channel(0).unwrap(), float(&left[1000..1500]).as_slice());
+    assert_eq!(decoder.channel(1).unwrap(), float(&right[1000..1500]).as_slice());
+
+    assert_eq!(decoder.pull_at(2800, 500), 200);
+    assert_eq!(decoder.pull_at(3000, 500), 0);
+    assert_eq!(decoder.pull(3000), 3000);
+}
+
+#[test]
+fn changes_the_playback_rate() {
+    let samples = ramp(10_000, 16);
+    let data = Flac::new(44_100, 16, vec![samples.clone()]).build();
+    let decoded = |rate| {
+        let mut decoder = Decoder::new(data.clone().into_boxed_slice()).unwrap();
+        decoder.set_playback_rate(rate).unwrap();
+        decoder.push(&[]).unwrap();
+        let mut pulled = Vec::new();
+        while decoder.pull(1000) > 0 {
+            pulled.extend_from_slice(decoder.channel(0).unwrap());
+        }
+        assert_eq!(decoder.available_samples(), 0);
+        pulled
+    };
+    let at = |i: usize| samples[i] as f32 / 32768.0;
+
+    let normal = decoded(1.0);
+    assert_eq!(normal, (0..10_000).map(at).collect::<Vec<_>>());
+    let double = decoded(2.0);
+    assert_eq!(double.len(), 5000);
+    assert_eq!((double[0], double[1], double[4999]), (at(0), at(2), at(9998)));
+    let half = decoded(0.5);
+    // The last frame plays too, with nothing after it to interpolate towards.
+    assert_eq!(half.len(), 2 * 9999 + 1);
+    assert_eq!(half[3], (at(1) + at(2)) / 2.0);
+    assert_eq!(half[2 * 9999], at(9999));
+
+    // Going back to the normal rate drops the fractional read position.
+    let mut decoder = Decoder::new(data.clone().into_boxed_slice()).unwrap();
+    decoder.push(&[]).unwrap();
+    decoder.set_playback_rate(0.5).unwrap();
+    assert_eq!(decoder.pull(3), 3);
+    decoder.set_playback_rate(1.0).unwrap();
+    decoder.pull(1);
+    assert_eq!(decoder.channel(0).unwrap(), [at(1)]);
+    decoder.set_playback_rate(2.0).unwrap();
+    decoder.pull(1);
+    assert_eq!(decoder.channel(0).unwrap(), [at(2)]);
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    for rate in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+        let err = decoder.set_playback_rate(rate).err();
+        assert_eq!(err.map(|e| e.code()), Some("invalid_parameter"));
+    }
+}
+
+#[test]
+fn pull_fills_channel_buffers() {
+    let left = ramp(1000, 16);
+    let right: Vec<i32> = left.iter().map(|s| s / 4).collect();
+    let data = Flac::new(44_100, 16, vec![left.clone(), right.clone()]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.push(&[]).unwrap();
+
+    assert_eq!(decoder.pull(256), 256);
+    let expected = |samples: &[i32]| samples.iter().map(|&s| s as f32 / 32768.0).collect::<Vec<_>>();
+    assert_eq!(decoder.channel(0).unwrap(), expected(&left[..256]).as_slice());
+    assert_eq!(decoder.channel(1).unwrap(), expected(&right[..256]).as_slice());
+
+    assert_eq!(decoder.pull(256), 256);
+    assert_eq!(decoder.channel(0).unwrap(), expected(&left[256..512]).as_slice());
+}
+
+#[test]
+fn channel_buffers_hold_only_pulled_frames() {
+    let data = Flac::new(44_100, 16, vec![ramp(1000, 16), ramp(1000, 16)]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.push(&[]).unwrap();
+
+    assert_eq!(decoder.pull(300), 300);
+    assert_eq!(decoder.channel(0).unwrap().len(), 300);
+    assert_eq!(decoder.pull(1000), 700);
+    assert_eq!(decoder.channel(1).unwrap().len(), 700);
+    assert_eq!(decoder.pull(1000), 0);
+    assert!(decoder.channel(0).unwrap().is_empty());
+}
+
+#[test]
+fn copies_pulled_channels_into_caller_buffers() {
+    let left = ramp(500, 16);
+    let right: Vec<i32> = left.iter().map(|s| -s / 2).collect();
+    let data = Flac::new(44_100, 16, vec![left.clone(), right.clone()]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.push(&[]).unwrap();
+    decoder.pull(128);
+
+    let mut dest = [0.0f32; 256];
+    assert_eq!(decoder.copy_left_into(&mut dest).unwrap(), 128);
+    assert_eq!(&dest[..128], decoder.channel(0).unwrap());
+
+    let mut short = [0.0f32; 16];
+    assert_eq!(decoder.copy_right_into(&mut short).unwrap(), 16);
+    assert_eq!(short[15], right[15] as f32 / 32768.0);
+}
+
+#[test]
+fn pulls_straight_into_caller_buffers() {
+    let left = ramp(500, 16);
+    let right: Vec<i32> = left.iter().map(|s| -s / 2).collect();
+    let data = Flac::new(44_100, 16, vec![left.clone(), right.clone()]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.push(&[]).unwrap();
+
+    let (mut l, mut r) = ([0.0f32; 300], [0.0f32; 300]);
+    assert_eq!(decoder.pull_into_left(&mut l).unwrap(), 300);
+    assert_eq!(decoder.pull_into_right(&mut r).unwrap(), 300);
+    assert_eq!(l[299], left[299] as f32 / 32768.0);
+    assert_eq!(r[299], right[299] as f32 / 32768.0);
+    assert_eq!(decoder.available_samples(), 200);
+
+    assert_eq!(decoder.pull_into_left(&mut l).unwrap(), 200);
+    assert_eq!(decoder.pull_into_right(&mut r).unwrap(), 200);
+    assert_eq!(r[0], right[300] as f32 / 32768.0);
+    assert_eq!(decoder.available_samples(), 0);
+}
+
+#[test]
+fn keeps_the_sides_aligned_when_pulling_unequal_amounts() {
+    let left = ramp(1000, 16);
+    let right: Vec<i32> = left.iter().map(|s| -s / 2).collect();
+    let data = Flac::new(44_100, 16, vec![left.clone(), right.clone()]).build();
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.push(&[]).unwrap();
+
+    let (mut pulled_left, mut pulled_right) = (Vec::new(), Vec::new());
+    for &(l, r) in &[(300, 100), (50, 400), (0, 250), (700, 10)] {
+        let mut dest = vec![0.0f32; l];
+        let n = decoder.pull_into_left(&mut dest).unwrap();
+        pulled_left.extend_from_slice(&dest[..n]);
+        let mut dest = vec![0.0f32; r];
+        let n = decoder.pull_into_right(&mut dest).unwrap();
+        pulled_right.extend_from_slice(&dest[..n]);
+    }
+    let mut dest = vec![0.0f32; 1000];
+    let n = decoder.pull_into_right(&mut dest).unwrap();
+    pulled_right.extend_from_slice(&dest[..n]);
+
+    let expected = |samples: &[i32]| samples.iter().map(|&s| s as f32 / 32768.0).collect::<Vec<_>>();
+    assert_eq!(pulled_left, expected(&left));
+    assert_eq!(pulled_right, expected(&right));
+    assert_eq!(decoder.pulled_samples(), 1000);
+    assert_eq!(decoder.available_samples(), 0);
+}
+
+#[test]
+fn reports_available_samples() {
+    let data = Flac::new(44_100, 16, vec![ramp(5000, 16), ramp(5000, 16)]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert_eq!(decoder.available_samples(), 0);
+    decoder.push(&[]).unwrap();
+    assert_eq!(decoder.available_samples(), 5000);
+    decoder.pull(1200);
+    assert_eq!(decoder.available_samples(), 3800);
+    decoder.pull_interleaved(5000);
+    assert_eq!(decoder.available_samples(), 0);
+}
+
+#[test]
+fn reports_buffered_duration() {
+    let data = Flac::new(48_000, 16, vec![ramp(24_000, 16)]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert_eq!(decoder.buffered_seconds(), 0.0);
+    decoder.push(&[]).unwrap();
+    assert_eq!(decoder.buffered_seconds(), 0.5);
+    decoder.pull(14_400);
+    assert_eq!(decoder.buffered_seconds(), 0.2);
+}
+
+#[test]
+fn decodes_again_after_reset() {
+    let flac = Flac::new(44_100, 16, vec![ramp(5000, 16), ramp(5000, 16)]).block_size(1024);
+
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    assert_eq!(decoder.push(&flac.frames().concat()).unwrap(), 5000);
+    decoder.pull(64);
+    let first = decoder.channel(0).unwrap().to_vec();
+
+    decoder.reset();
+    assert_eq!(decoder.available_samples(), 0);
+    assert!(decoder.channel(0).unwrap().is_empty());
+    assert_eq!(decoder.sample_rate(), 44_100);
+
+    assert_eq!(decoder.push(&flac.frames().concat()).unwrap(), 5000);
+    decoder.pull(64);
+    assert_eq!(decoder.channel(0).unwrap(), &first[..]);
+}
+
+#[test]
+fn flush_drops_buffered_input_and_output() {
+    let flac = Flac::new(44_100, 16, vec![ramp(5000, 16)]).block_size(1024);
+    let frames = flac.frames();
+
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    decoder.push(&frames[0]).unwrap();
+    decoder.push(&frames[1][..10]).unwrap();
+    decoder.flush();
+    assert_eq!(decoder.available_samples(), 0);
+
+    // The partial frame is gone, so the next whole frame decodes cleanly.
+    assert_eq!(decoder.push(&frames[2]).unwrap(), 1024);
+    assert_eq!(decoder.sample(0, 0), Some(ramp(5000, 16)[2048] as f32 / 32768.0));
+}
+
+#[test]
+fn normalizes_full_scale_at_every_bit_depth() {
+    for bits in [8, 12, 16, 20, 24] {
+        let max = (1 << (bits - 1)) - 1;
+        let data = Flac::new(44_100, bits, vec![vec![max, -max - 1, 0, 1]]).build();
+
+        let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+        decoder.push(&[]).unwrap();
+
+        let lsb = 1.0 / (1 << (bits - 1)) as f32;
+        assert_eq!(decoder.sample(0, 0), Some(1.0 - lsb), "{} bits", bits);
+        assert_eq!(decoder.sample(0, 1), Some(-1.0), "{} bits", bits);
+        assert_eq!(decoder.sample(0, 2), Some(0.0), "{} bits", bits);
+        assert_eq!(decoder.sample(0, 3), Some(lsb), "{} bits", bits);
+    }
+}
+
+#[test]
+fn dither_changes_lsbs_but_keeps_the_mean() {
+    let samples = common::sine(20_000, 441.0, 0.3, 24);
+    let data = Flac::new(44_100, 24, vec![samples]).build();
+
+    let pull = |mode| {
+        let mut decoder = Decoder::new(data.clone().into_boxed_slice()).unwrap();
+        decoder.set_dither(mode);
+        decoder.push(&[]).unwrap();
+        decoder.pull_i16(20_000);
+        decoder.channel_i16(0).unwrap().to_vec()
+    };
+    let plain = pull(DitherMode::None);
+    let dithered = pull(DitherMode::TriangularPDF);
+
+    assert_eq!(dithered, pull(DitherMode::TriangularPDF));
+    assert!(plain.iter().zip(&dithered).filter(|(a, b)| a != b).count() > 5000);
+    assert!(plain.iter().zip(&dithered).all(|(&a, &b)| (a as i32 - b as i32).abs() <= 2));
+
+    let mean = |samples: &[i16]| samples.iter().map(|&s| s as f64).sum::<f64>() / samples.len() as f64;
+    assert!((mean(&plain) - mean(&dithered)).abs() < 0.05);
+}
+
+#[test]
+fn tracks_peaks_while_decoding() {
+    let mut left = vec![0; 32];
+    let mut right = vec![0; 32];
+    left[..3].copy_from_slice(&[8192, -16384, 100]);
+    right[..2].copy_from_slice(&[-4096, 2048]);
+    left[20] = -2048;
+    let flac = Flac::new(44_100, 16, vec![left, right]).block_size(16);
+    let frames = flac.frames();
+
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    assert_eq!(decoder.peak_left(), 0.0);
+    decoder.push(&frames[0]).unwrap();
+    assert_eq!(decoder.peak_left(), 0.5);
+    assert_eq!(decoder.peak_right(), 0.125);
+
+    decoder.reset_peaks();
+    assert_eq!(decoder.peak_levels(), &[0.0, 0.0]);
+    decoder.push(&frames[1]).unwrap();
+    assert_eq!(decoder.peak_levels(), &[0.0625, 0.0]);
+}
+
+#[test]
+fn decodes_a_whole_file_at_once() {
+    let left = ramp(10_000, 16);
+    let right: Vec<i32> = left.iter().map(|s| s / 2).collect();
+    let data = Flac::new(48_000, 16, vec![left.clone(), right]).block_size(1152).build();
+    let total = Decoder::new(data.clone().into_boxed_slice()).unwrap().total_samples();
+
+    let audio = Decoder::decode_all(data.into_boxed_slice()).unwrap();
+    assert_eq!(audio.frames() as u64, total);
+    assert_eq!((audio.sample_rate(), audio.channels(), audio.bit_depth()), (48_000, 2, 16));
+    assert_eq!(audio.channel(0).unwrap()[9999], left[9999] as f32 / 32768.0);
+    assert!(audio.channel(2).is_none());
+}
+
+#[test]
+fn decodes_a_whole_file_of_unknown_length_the_same() {
+    let channels = vec![ramp(9000, 16), common::sine(9000, 300.0, 0.8, 16)];
+    let flac = Flac::new(44_100, 16, channels).block_size(4096);
+    let known = Decoder::decode_all(flac.build().into_boxed_slice()).unwrap();
+    let unknown = Decoder::decode_all(flac.total_samples(0).build().into_boxed_slice()).unwrap();
+
+    assert_eq!(known.frames(), 9000);
+    assert_eq!(unknown.frames(), 9000);
+    assert_eq!(known.channel(0), unknown.channel(0));
+    assert_eq!(known.channel(1), unknown.channel(1));
+}
+
+#[test]
+fn decodes_a_whole_file_with_an_overstated_length() {
+    let samples = ramp(9000, 16);
+    let data = Flac::new(44_100, 16, vec![samples.clone()]).total_samples((1 << 36) - 1).build();
+    let audio = Decoder::decode_all(data.into_boxed_slice()).unwrap();
+
+    assert_eq!(audio.frames(), 9000);
+    let expected: Vec<f32> = samples.iter().map(|&s| s as f32 / 32768.0).collect();
+    assert_eq!(audio.channel(0), Some(expected.as_slice()));
+}
+
+#[test]
+fn reads_single_samples_of_decoded_audio() {
+    let left = ramp(3000, 16);
+    let right: Vec<i32> = left.iter().map(|s| -s).collect();
+    let data = Flac::new(48_000, 16, vec![left, right]).build();
+    let audio = Decoder::decode_all(data.into_boxed_slice()).unwrap();
+
+    for &(channel, index) in &[(0, 0), (0, 1234), (1, 2999)] {
+        assert_eq!(audio.get_sample(channel, index as u64), audio.channel(channel as usize).unwrap()[index]);
+    }
+    assert!(audio.get_sample(0, 3000).is_nan());
+    assert!(audio.get_sample(2, 0).is_nan());
+}
+
+#[test]
+fn trims_silence_from_decoded_audio() {
+    let tone = common::sine(1000, 50.0, 0.5, 16);
+    let mut samples = vec![0; 700];
+    samples.extend(&tone);
+    samples.extend(vec![3; 500]);
+    let data = Flac::new(48_000, 16, vec![samples.clone(), vec![0; 2200]]).build();
+    let mut audio = Decoder::decode_all(data.into_boxed_slice()).unwrap();
+
+    let first = tone.iter().position(|&s| s != 0).unwrap();
+    let last = tone.iter().rposition(|&s| s != 0).unwrap();
+    assert_eq!(&audio.trim_silence(-60.0)[..], &[700 + first, 700 + last + 1]);
+    assert_eq!(audio.pull(5000), last + 1 - first);
+    assert_eq!(audio.pulled(0).unwrap()[0], samples[700 + first] as f32 / 32768.0);
+    assert_eq!(audio.frames(), 2200);
+
+    let data = Flac::new(48_000, 16, vec![vec![0; 1000]]).build();
+    let mut silent = Decoder::decode_all(data.into_boxed_slice()).unwrap();
+    assert_eq!(&silent.trim_silence(-60.0)[..], &[0, 0]);
+    assert_eq!(silent.pull(100), 0);
+}
+
+#[test]
+fn loops_a_region_of_decoded_audio() {
+    let samples = ramp(1000, 16);
+    let data = Flac::new(44_100, 16, vec![samples.clone()]).build();
+    let mut audio = Decoder::decode_all(data.into_boxed_slice()).unwrap();
+    let at = |i: usize| samples[i] as f32 / 32768.0;
+
+    assert_eq!(audio.set_loop(500, 400).err().map(|err| err.code()), Some("invalid_loop"));
+    assert_eq!(audio.set_loop(0, 1001).err().map(|err| err.code()), Some("invalid_loop"));
+
+    audio.set_loop(200, 300).unwrap();
+    assert_eq!(audio.pull(250), 250);
+    assert_eq!(audio.pulled(0).unwrap()[249], at(249));
+    assert_eq!(audio.pull(100), 100);
+    let pulled = audio.pulled(0).unwrap();
+    assert_eq!((pulled[49], pulled[50]), (at(299), at(200)));
+    assert_eq!(audio.position(), 250);
+
+    audio.clear_loop();
+    assert_eq!(audio.pull(2000), 750);
+    assert_eq!(audio.pull(10), 0);
+}
+
+#[test]
+fn plays_decoded_audio_in_reverse() {
+    let left = ramp(1000, 16);
+    let right: Vec<i32> = left.iter().map(|s| -s).collect();
+    let data = Flac::new(44_100, 16, vec![left.clone(), right.clone()]).build();
+    let mut audio = Decoder::decode_all(data.into_boxed_slice()).unwrap();
+    let reversed = |samples: &[i32]| -> Vec<f32> { samples.iter().rev().map(|&s| s as f32 / 32768.0).collect() };
+
+    audio.set_reverse(true);
+    audio.seek(2000);
+    assert_eq!(audio.position(), 1000);
+    assert_eq!(audio.pull(300), 300);
+    assert_eq!(audio.pulled(0).unwrap(), reversed(&left[700..]).as_slice());
+    assert_eq!(audio.pulled(1).unwrap(), reversed(&right[700..]).as_slice());
+
+    audio.seek(100);
+    assert_eq!(audio.pull(300), 100);
+    assert_eq!(audio.pulled(0).unwrap(), reversed(&left[..100]).as_slice());
+    assert_eq!(audio.pull(300), 0);
+
+    audio.set_loop(200, 300).unwrap();
+    assert_eq!(audio.pull(150), 150);
+    let pulled = audio.pulled(0).unwrap();
+    assert_eq!((pulled[99], pulled[100]), (left[200] as f32 / 32768.0, left[299] as f32 / 32768.0));
+}
+
+#[test]
+fn decodes_frames_of_changing_size_in_sequence() {
+    let left = ramp(9000, 16);
+    let right: Vec<i32> = left.iter().map(|s| -s - 1).collect();
+    let data = Flac::new(44_100, 16, vec![left.clone(), right.clone()])
+        .variable_block_sizes(&[4096, 16, 1000, 3888])
+        .build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert_eq!(decoder.push(&[]).unwrap(), 9000);
+    decoder.pull(9000);
+    let expected = |samples: &[i32]| samples.iter().map(|&s| s as f32 / 32768.0).collect::<Vec<_>>();
+    assert_eq!(decoder.channel(0).unwrap(), &expected(&left)[..]);
+    assert_eq!(decoder.channel(1).unwrap(), &expected(&right)[..]);
+}
+
+#[test]
+fn reports_the_start_sample_of_each_frame() {
+    let data = Flac::new(44_100, 16, vec![ramp(9000, 16)])
+        .variable_block_sizes(&[4096, 16, 1000, 3888])
+        .build();
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert_eq!(decoder.last_frame_start_sample(), 0);
+
+    let mut frames = Vec::new();
+    decoder.push_with_frames(&[], |decoder, pulled| {
+        frames.push((decoder.last_frame_start_sample(), pulled as u64));
+        Ok(())
+    }).unwrap();
+    assert_eq!(frames, [(0, 4096), (4096, 16), (4112, 1000), (5112, 3888)]);
+    // Each frame starts where the one before it ended.
+    assert!(frames.windows(2).all(|pair| pair[0].0 + pair[0].1 == pair[1].0));
+}
+
+#[test]
+fn iterates_over_the_frames_of_a_file() {
+    let left = ramp(9000, 16);
+    let right: Vec<i32> = left.iter().map(|s| -s - 1).collect();
+    let flac = Flac::new(44_100, 16, vec![left.clone(), right.clone()]).block_size(4096);
+    let frames = flac.frames();
+    let mut data = flac.build();
+    data.truncate(data.len() - frames[2].len() / 2);
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+
+    let decoded: Vec<Frame> = decoder.frames().collect();
+    assert_eq!(decoded.iter().map(Frame::samples).collect::<Vec<_>>(), [4096, 4096]);
+    assert_eq!(decoded[1].start_sample(), 4096);
+    assert_eq!(decoded[1].channels(), 2);
+    assert_eq!(decoded[1].channel(0), Some(&left[4096..8192]));
+    assert_eq!(decoded[1].channel(1), Some(&right[4096..8192]));
+    assert_eq!(decoded[1].channel(2), None);
+    assert_eq!(decoder.sample_position(), 8192);
+
+    // The incomplete last frame waits for the rest of its bytes.
+    decoder.push(&frames[2][frames[2].len() - frames[2].len() / 2..]).unwrap();
+    assert_eq!(decoder.available_samples(), 9000 - 8192);
+}
+
+#[test]
+fn primes_with_a_single_frame() {
+    let flac = Flac::new(44_100, 16, vec![ramp(10_000, 16)]).block_size(4096);
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    assert_eq!(decoder.prime().unwrap(), 0);
+
+    let mut decoder = Decoder::new(flac.build().into_boxed_slice()).unwrap();
+    assert_eq!(decoder.prime().unwrap(), 4096);
+    assert_eq!(decoder.available_samples(), 4096);
+    assert_eq!(decoder.push(&[]).unwrap(), 10_000 - 4096);
+}
+
+#[test]
+fn keeps_a_partial_frame_buffered_until_it_completes() {
+    let samples = ramp(5000, 16);
+    let flac = Flac::new(44_100, 16, vec![samples.clone()]).block_size(1024);
+    let frames = flac.frames();
+    let (head, tail) = frames[2].split_at(frames[2].len() / 2);
+
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    let mut first = frames[..2].concat();
+    first.extend_from_slice(head);
+    assert_eq!(decoder.push(&first).unwrap(), 2048);
+    assert_eq!(decoder.buffered_input_len(), head.len());
+
+    let mut rest = tail.to_vec();
+    rest.extend(frames[3..].concat());
+    assert_eq!(decoder.push(&rest).unwrap(), 5000 - 2048);
+    assert_eq!(decoder.buffered_input_len(), 0);
+    assert_eq!(decoder.sample(0, 2048), Some(samples[2048] as f32 / 32768.0));
+}
+
+#[test]
+fn peeks_at_the_duration_of_the_next_frame() {
+    let flac = Flac::new(44_100, 16, vec![ramp(5000, 16)]).block_size(1152);
+    let frames = flac.frames();
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    assert_eq!(decoder.peek_next_frame_duration(), None);
+
+    decoder.push(&frames[0][..2]).unwrap();
+    assert_eq!(decoder.peek_next_frame_duration(), None);
+    decoder.push(&frames[0][2..]).unwrap();
+    decoder.push(&frames[1..].concat()).unwrap();
+    assert_eq!(decoder.peek_next_frame_duration(), None);
+
+    let mut decoder = Decoder::new(flac.build().into_boxed_slice()).unwrap();
+    assert_eq!(decoder.peek_next_frame_duration(), Some(1152));
+    assert_eq!(decoder.available_samples(), 0);
+    decoder.set_max_decode_per_push(Some(4000));
+    decoder.push(&[]).unwrap();
+    // The last frame holds what is left over after four full blocks.
+    assert_eq!(decoder.peek_next_frame_duration(), Some(5000 - 4 * 1152));
+}
+
+#[test]
+fn hints_at_the_bytes_needed_for_the_next_frame() {
+    let flac = Flac::new(44_100, 16, vec![ramp(5000, 16)]).block_size(1024);
+    let frames = flac.frames();
+    let max_frame = frames.iter().map(Vec::len).max().unwrap();
+
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    assert_eq!(decoder.bytes_needed(), max_frame);
+    decoder.push(&frames[0][..10]).unwrap();
+    assert_eq!(decoder.bytes_needed(), max_frame - 10);
+}
+
+#[test]
+fn streaming_in_small_chunks_matches_a_single_push() {
+    // The reference encoder writes a SEEKTABLE by default.
+    for seektable in [false, true] {
+        let left = common::sine(20_000, 123.0, 0.7, 16);
+        let right = ramp(20_000, 16);
+        let mut flac = Flac::new(44_100, 16, vec![left, right]).block_size(1152);
+        if seektable {
+            flac = flac.with_seektable(4);
+        }
+        let whole = Decoder::decode_all(flac.build().into_boxed_slice()).unwrap();
+
+        let frames = flac.frames().concat();
+        let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+        let (mut decoded, mut peak_memory) = (0, 0);
+        let (mut left, mut right) = (Vec::new(), Vec::new());
+        for chunk in frames.chunks(512) {
+            decoded += decoder.push(chunk).unwrap();
+            decoder.pull(usize::MAX);
+            left.extend_from_slice(decoder.channel(0).unwrap());
+            right.extend_from_slice(decoder.channel(1).unwrap());
+            peak_memory = peak_memory.max(decoder.memory_usage());
+        }
+        assert_eq!(decoded, 20_000);
+        assert_eq!(left, whole.channel(0).unwrap());
+        assert_eq!(right, whole.channel(1).unwrap());
+        // Decoded input is dropped as more arrives instead of piling up.
+        assert!(peak_memory < frames.len() / 2, "{} of {} bytes", peak_memory, frames.len());
+    }
+}
+
+#[test]
+fn keeps_pushed_input_for_seeking_when_asked() {
+    let samples = ramp(20_000, 16);
+    let flac = Flac::new(44_100, 16, vec![samples.clone()]).block_size(1152).with_seektable(4);
+    let frames = flac.frames().concat();
+
+    let mut streamed = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    let mut kept = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    kept.set_keep_input(true);
+    for chunk in frames.chunks(512) {
+        streamed.push(chunk).unwrap();
+        kept.push(chunk).unwrap();
+    }
+
+    assert_eq!(streamed.seek(100).err().map(|err| err.code()), Some("not_buffered"));
+    assert_eq!(kept.seek(100).unwrap(), 100);
+    assert_eq!(kept.sample(0, 0), Some(samples[100] as f32 / 32768.0));
+    assert!(kept.memory_usage() > frames.len());
+}
+
+#[test]
+fn counts_decoded_frames_and_bytes() {
+    let flac = Flac::new(44_100, 16, vec![ramp(10_000, 16)]).block_size(1152);
+    let frames = flac.frames().concat();
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    for chunk in frames.chunks(512) {
+        decoder.push(chunk).unwrap();
+    }
+
+    let stats = decoder.decode_stats();
+    assert_eq!(stats, DecodeStats {
+        frames: 9,
+        samples: 10_000,
+        bytes: frames.len() as u64,
+        pushes: frames.chunks(512).len() as u64,
+    });
+    assert!(stats.frames * 1152 >= stats.samples && (stats.frames - 1) * 1152 < stats.samples);
+
+    decoder.reset();
+    assert_eq!(decoder.decode_stats(), DecodeStats::default());
+}
+
+#[test]
+fn tracks_the_decode_position() {
+    let flac = Flac::new(8000, 16, vec![ramp(10_000, 16)]).block_size(1000);
+    let frames = flac.frames();
+
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    decoder.push(&frames[..3].concat()).unwrap();
+    assert_eq!(decoder.sample_position(), 3000);
+    decoder.push(&frames[3..6].concat()).unwrap();
+    assert_eq!(decoder.sample_position(), 6000);
+    assert_eq!(decoder.time_position(), 0.75);
+
+    decoder.pull(6000);
+    assert_eq!(decoder.sample_position(), 6000);
+    decoder.reset();
+    assert_eq!(decoder.sample_position(), 0);
+}
+
+#[test]
+fn decodes_without_streaminfo() {
+    let samples = ramp(5000, 16);
+    let data = Flac::new(32_000, 16, vec![samples.clone(), samples.clone()])
+        .block_size(1024)
+        .without_streaminfo()
+        .metadata(common::PADDING, vec![0; 4])
+        .build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert_eq!((decoder.sample_rate(), decoder.channels(), decoder.bit_depth()), (32_000, 2, 16));
+    assert_eq!(decoder.total_samples(), 0);
+    assert_eq!(decoder.push(&[]).unwrap(), 5000);
+    assert_eq!(decoder.sample(1, 4999), Some(samples[4999] as f32 / 32768.0));
+}
+
+#[test]
+fn decodes_raw_frames_with_given_parameters() {
+    let left = ramp(5000, 24);
+    let right: Vec<i32> = left.iter().map(|s| -s).collect();
+    let flac = Flac::new(96_000, 24, vec![left.clone(), right]).block_size(1152);
+    let frames = flac.frames();
+    let (first, rest) = frames.split_at(2);
+
+    let mut decoder = Decoder::new_raw(first.concat().into_boxed_slice(), 96_000, 2, 24).unwrap();
+    assert_eq!((decoder.sample_rate(), decoder.channels(), decoder.bit_depth()), (96_000, 2, 24));
+    assert_eq!(decoder.max_block_size(), 1152);
+    assert_eq!(decoder.push(&rest.concat()).unwrap(), 5000);
+    assert_eq!(decoder.sample(0, 4999), Some(left[4999] as f32 / 8_388_608.0));
+
+    assert_eq!(Decoder::new_raw(Box::new([]), 44_100, 9, 16).err().map(|err| err.code()), Some("unsupported_channels"));
+}
+
+#[test]
+fn hands_over_each_frame_as_it_is_decoded() {
+    let samples = ramp(5000, 16);
+    let flac = Flac::new(44_100, 16, vec![samples.clone()]).block_size(1152);
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+
+    let mut frames = Vec::new();
+    let mut decoded = Vec::new();
+    let total = decoder.push_with_frames(&flac.frames().concat(), |decoder, len| {
+        frames.push(len);
+        decoded.extend_from_slice(decoder.channel(0).unwrap());
+        Ok(())
+    });
+    assert_eq!(total.unwrap(), 5000);
+    assert_eq!(frames, vec![1152, 1152, 1152, 1152, 392]);
+    assert_eq!(decoded, samples.iter().map(|&s| s as f32 / 32768.0).collect::<Vec<_>>());
+    assert_eq!(decoder.available_samples(), 0);
+}
+
+#[test]
+fn limits_the_frames_decoded_per_push() {
+    let data = Flac::new(44_100, 16, vec![ramp(10_000, 16)]).block_size(1000).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.set_max_decode_per_push(Some(2500));
+    let mut pushes = Vec::new();
+    loop {
+        match decoder.push(&[]).unwrap() {
+            0 => break,
+            decoded => pushes.push(decoded),
+        }
+    }
+    assert_eq!(pushes, [3000, 3000, 3000, 1000]);
+    assert_eq!(decoder.available_samples(), 10_000);
+}
+
+#[test]
+fn output_limit_holds_back_decoding() {
+    let samples = ramp(10_000, 16);
+    let flac = Flac::new(44_100, 16, vec![samples.clone()]).block_size(1000);
+
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    decoder.set_output_limit(Some(2500));
+    let mut pulled = Vec::new();
+    for chunk in flac.frames().concat().chunks(4096).chain(std::iter::repeat_n(&[][..], 20)) {
+        decoder.push(chunk).unwrap();
+        assert!(decoder.available_samples() <= 2500);
+        decoder.pull(700);
+        pulled.extend_from_slice(decoder.channel(0).unwrap());
+    }
+
+    let expected: Vec<f32> = samples.iter().map(|&s| s as f32 / 32768.0).collect();
+    assert_eq!(pulled, expected);
+}
+
+#[test]
+fn pulls_more_than_one_block_at_a_time() {
+    let samples = ramp(70_000, 16);
+    let data = Flac::new(44_100, 16, vec![samples.clone()]).build();
+
+    let mut decoder = Decoder::new_with_capacity(data.into_boxed_slice(), 65_536).unwrap();
+    decoder.push(&[]).unwrap();
+    assert_eq!(decoder.pull(65_536), 65_536);
+    assert_eq!(decoder.channel(0).unwrap().len(), 65_536);
+    assert_eq!(decoder.channel(0).unwrap()[65_535], samples[65_535] as f32 / 32768.0);
+    assert_eq!(decoder.pull(65_536), 70_000 - 65_536);
+}
+
+#[test]
+fn continues_gaplessly_into_a_concatenated_stream() {
+    let (first, second) = (ramp(3000, 16), common::sine(2500, 100.0, 0.5, 16));
+    let next = Flac::new(44_100, 16, vec![second.clone()]).block_size(1024).build();
+
+    let mut decoder = Decoder::new(Flac::new(44_100, 16, vec![first.clone()]).build().into_boxed_slice()).unwrap();
+    decoder.set_gapless(true);
+    assert_eq!(decoder.push(&[]).unwrap(), 3000);
+    let mut decoded = 0;
+    for chunk in next.chunks(100) {
+        decoded += decoder.push(chunk).unwrap();
+    }
+    assert_eq!(decoded, 2500);
+    assert_eq!(decoder.sample_position(), 5500);
+
+    assert_eq!(decoder.pull(6000), 5500);
+    let expected: Vec<f32> = first.iter().chain(&second).map(|&s| s as f32 / 32768.0).collect();
+    assert_eq!(decoder.channel(0).unwrap(), &expected[..]);
+}
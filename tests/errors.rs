@@ -0,0 +1,176 @@
+mod common;
+
+use common::{sine, Flac, PADDING};
+use flac_decoder::{DecodeError, Decoder};
+
+fn error_code(data: Vec<u8>) -> &'static str {
+    match Decoder::new(data.into_boxed_slice()) {
+        Ok(_) => panic!("decoder accepted invalid input"),
+        Err(err) => err.code(),
+    }
+}
+
+#[test]
+fn rejects_wrong_magic() {
+    let mut data = Flac::new(44_100, 16, vec![sine(100, 50.0, 0.5, 16)]).build();
+    data[..4].copy_from_slice(b"RIFF");
+
+    assert_eq!(error_code(data), "bad_magic");
+}
+
+#[test]
+fn names_the_format_of_files_that_are_not_flac() {
+    let message = |data: &[u8]| match Decoder::new(data.into()) {
+        Ok(_) => panic!("decoder accepted invalid input"),
+        Err(err) => (err.code(), err.to_string()),
+    };
+
+    let mut wav = b"RIFF\x24\x10\0\0WAVEfmt \x10\0\0\0".to_vec();
+    wav.resize(44, 0);
+    assert_eq!(message(&wav), ("bad_magic", "Expected FLAC, found what looks like a WAV file".to_string()));
+    // An MPEG-1 Layer III frame header, 128 kbit/s at 44.1 kHz.
+    let mp3 = [0xff, 0xfb, 0x90, 0x64, 0, 0, 0, 0];
+    assert_eq!(message(&mp3), ("bad_magic", "Expected FLAC, found what looks like an MP3 file".to_string()));
+    assert_eq!(message(b"\x01\x02abcd").1, "Expected FLAC, but the data starts with \"\\x01\\x02ab\" instead of \"fLaC\"");
+}
+
+#[test]
+fn rejects_truncated_metadata() {
+    let header = Flac::new(44_100, 16, vec![sine(100, 50.0, 0.5, 16)]).header();
+
+    // Cut the STREAMINFO block off halfway through.
+    assert_eq!(error_code(header[..4 + 4 + 17].to_vec()), "io");
+    assert_eq!(error_code(b"fL".to_vec()), "io");
+}
+
+#[test]
+fn rejects_missing_stream_info() {
+    let header = Flac::new(44_100, 16, vec![sine(100, 50.0, 0.5, 16)]).without_streaminfo()
+        .metadata(PADDING, vec![0; 8])
+        .header();
+
+    assert_eq!(error_code(header), "missing_stream_info");
+}
+
+#[test]
+fn rejects_missing_stream_info_the_frames_depend_on() {
+    // The frame headers cannot express 4-bit samples, so they refer to STREAMINFO instead.
+    let data = Flac::new(44_100, 4, vec![vec![0, 1, -1, 7]]).without_streaminfo()
+        .metadata(PADDING, vec![0; 8])
+        .build();
+
+    assert_eq!(error_code(data), "stream_info_required");
+}
+
+#[test]
+fn garbage_frames_fail_instead_of_spinning() {
+    let flac = Flac::new(44_100, 16, vec![sine(100, 50.0, 0.5, 16)]);
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+
+    // Claxon always consumes a frame header, so no bytes can make a frame read nothing; the unit
+    // test in lib.rs covers that with a reader that stalls. Input that never forms a frame fails
+    // in Claxon instead.
+    for garbage in [vec![0u8; 4096], vec![0xff; 4096]] {
+        assert_eq!(decoder.push(&garbage).err().map(|err| err.code()), Some("claxon"));
+        decoder.flush();
+    }
+}
+
+#[test]
+fn recovers_from_a_corrupt_frame() {
+    let samples = common::ramp(10_000, 16);
+    let flac = Flac::new(44_100, 16, vec![samples.clone()]).block_size(1000);
+    let mut frames = flac.frames();
+    let middle = frames[4].len() / 2;
+    frames[4][middle..middle + 8].copy_from_slice(&[0xaa; 8]);
+
+    let mut strict = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    assert_eq!(strict.push(&frames.concat()).err().map(|err| err.code()), Some("claxon"));
+
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    decoder.set_error_recovery(true);
+    assert_eq!(decoder.push(&frames.concat()).unwrap(), 9000);
+    assert_eq!(decoder.sample_position(), 10_000);
+    // The frame after the corrupt one decodes in full.
+    assert_eq!(decoder.sample(0, 4000), Some(samples[5000] as f32 / 32768.0));
+}
+
+#[test]
+fn reports_sample_rate_changes() {
+    let first = Flac::new(44_100, 16, vec![common::ramp(2000, 16)]).block_size(1000);
+    let second = Flac::new(48_000, 16, vec![common::ramp(2000, 16)]).block_size(1000);
+    let mut frames = first.frames().concat();
+    frames.extend(second.frames().concat());
+
+    let mut decoder = Decoder::new(first.header().into_boxed_slice()).unwrap();
+    match decoder.push(&frames) {
+        Err(DecodeError::ParameterChange { expected, found }) => {
+            assert_eq!(expected.sample_rate, 44_100);
+            assert_eq!(found.sample_rate, 48_000);
+            assert_eq!(found.channels, 1);
+        }
+        _ => panic!("sample rate change went unnoticed"),
+    }
+    assert_eq!(decoder.available_samples(), 2000);
+
+    let mut decoder = Decoder::new(first.header().into_boxed_slice()).unwrap();
+    decoder.set_follow_parameter_changes(true);
+    assert_eq!(decoder.push(&frames).unwrap(), 4000);
+    assert_eq!(decoder.sample_rate(), 48_000);
+}
+
+#[test]
+fn rejects_a_gapless_stream_in_another_format() {
+    let first = Flac::new(44_100, 16, vec![common::ramp(2000, 16)]).build();
+    let second = Flac::new(44_100, 24, vec![common::ramp(2000, 24)]).build();
+
+    let mut decoder = Decoder::new(first.into_boxed_slice()).unwrap();
+    decoder.set_gapless(true);
+    match decoder.push(&second) {
+        Err(DecodeError::ParameterChange { expected, found }) => {
+            assert_eq!(expected.bits_per_sample, 16);
+            assert_eq!(found.bits_per_sample, 24);
+        }
+        _ => panic!("format change between streams went unnoticed"),
+    }
+    assert_eq!(decoder.available_samples(), 2000);
+}
+
+#[test]
+fn counts_crc_failures() {
+    let flac = Flac::new(44_100, 16, vec![common::ramp(10_000, 16)]).block_size(1000);
+    let mut frames = flac.frames();
+    for frame in [2, 6] {
+        let last = frames[frame].len() - 1;
+        frames[frame][last] ^= 0xff;
+    }
+
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    decoder.set_error_recovery(true);
+    assert_eq!(decoder.push(&frames.concat()).unwrap(), 8000);
+    assert_eq!(decoder.crc_failures(), 2);
+
+    let mut strict = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    assert!(strict.push(&frames.concat()).is_err());
+    assert_eq!(strict.crc_failures(), 1);
+}
+
+#[test]
+fn rejects_stream_parameters_out_of_range() {
+    let raw = |sample_rate, bits| match Decoder::new_raw(Box::new([]), sample_rate, 2, bits) {
+        Ok(_) => panic!("decoder accepted {} Hz at {} bits", sample_rate, bits),
+        Err(DecodeError::InvalidStreamInfo { field, value }) => (field, value),
+        Err(err) => panic!("unexpected error {}", err),
+    };
+    assert_eq!(raw(0, 16), ("sample_rate", 0));
+    assert_eq!(raw(655_351, 16), ("sample_rate", 655_351));
+    assert_eq!(raw(44_100, 3), ("bits_per_sample", 3));
+    assert_eq!(raw(44_100, 33), ("bits_per_sample", 33));
+    assert!(Decoder::new_raw(Box::new([]), 655_350, 8, 32).is_ok());
+    let no_channels = Decoder::new_raw(Box::new([]), 44_100, 0, 16).err();
+    assert_eq!(no_channels.map(|err| err.code()), Some("unsupported_channels"));
+
+    // STREAMINFO stores the bit depth in five bits, so it can go below the minimum.
+    let header = Flac::new(44_100, 3, vec![vec![0; 16]]).header();
+    assert_eq!(error_code(header), "invalid_stream_info");
+}
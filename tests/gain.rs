@@ -0,0 +1,156 @@
+mod common;
+
+use common::{ramp, sine, Flac};
+use flac_decoder::{crossfade, Decoder, FadeCurve, OutputFormat};
+
+fn decoder(samples: Vec<i32>) -> Decoder {
+    let data = Flac::new(44_100, 16, vec![samples]).build();
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.push(&[]).unwrap();
+    decoder
+}
+
+#[test]
+fn gain_scales_pulled_samples() {
+    let samples = ramp(1000, 16);
+    let mut decoder = decoder(samples.clone());
+
+    decoder.set_gain(0.5);
+    decoder.pull(500);
+    for (&pulled, &sample) in decoder.channel(0).unwrap().iter().zip(&samples) {
+        assert_eq!(pulled, sample as f32 / 65536.0);
+    }
+
+    // Already buffered samples follow the new gain too.
+    decoder.set_gain(1.0);
+    decoder.pull_interleaved(500);
+    assert_eq!(decoder.interleaved()[0], samples[500] as f32 / 32768.0);
+
+    let mut decoder = self::decoder(samples.clone());
+    decoder.set_output_format(OutputFormat::I16);
+    decoder.set_gain(0.5);
+    decoder.pull(1000);
+    assert_eq!(decoder.channel_i16(0).unwrap()[1], (samples[1] as f32 / 2.0).round() as i16);
+}
+
+#[test]
+fn gain_in_decibels() {
+    let samples = ramp(1000, 16);
+    let mut decoder = decoder(samples.clone());
+
+    decoder.set_gain_db(-6.0).unwrap();
+    decoder.pull(1000);
+    for (&pulled, &sample) in decoder.channel(0).unwrap().iter().zip(&samples) {
+        assert!((pulled - sample as f32 / 65536.0).abs() < 0.003 * sample.abs() as f32 / 32768.0 + 1e-7);
+    }
+
+    for db in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+        assert_eq!(decoder.set_gain_db(db).err().map(|e| e.code()), Some("invalid_parameter"));
+    }
+}
+
+#[test]
+fn soft_clip_keeps_samples_within_full_scale() {
+    let samples = sine(1000, 100.0, 0.8, 16);
+    let mut decoder = decoder(samples.clone());
+    decoder.set_gain(2.0);
+    decoder.set_soft_clip(true);
+    decoder.pull(1000);
+
+    let channel = decoder.channel(0).unwrap();
+    assert!(channel.iter().all(|s| s.abs() < 1.0));
+    assert!(channel.iter().any(|s| s.abs() > 0.95));
+    // Quiet samples stay linear.
+    for (&pulled, &sample) in channel.iter().zip(&samples).filter(|(s, _)| s.abs() < 0.8) {
+        assert_eq!(pulled, sample as f32 / 16384.0);
+    }
+}
+
+#[test]
+fn limiter_folds_boosted_full_scale_peaks_smoothly() {
+    let mut decoder = decoder(sine(1000, 100.0, 1.0, 16));
+    decoder.set_gain(2.0);
+    decoder.set_limiter(true);
+    decoder.pull(1000);
+
+    let channel = decoder.channel(0).unwrap();
+    assert!(channel.iter().all(|s| s.abs() < 1.0));
+    // The limiter never steepens the signal and does not flatten its tops.
+    let steepest = 2.0 * std::f32::consts::TAU / 100.0;
+    assert!(channel.windows(2).all(|w| (w[1] - w[0]).abs() <= steepest));
+    assert!(channel.windows(3).all(|w| w[0] != w[1] || w[1] != w[2]));
+}
+
+#[test]
+fn normalizes_the_peak_of_decoded_audio() {
+    let data = Flac::new(44_100, 16, vec![sine(5000, 100.0, 0.25, 16), sine(5000, 80.0, 0.4, 16)]).build();
+    let mut audio = Decoder::decode_all(data.into_boxed_slice()).unwrap();
+    assert!((audio.peak() - 0.4).abs() < 0.001);
+
+    let gain = audio.normalize_peak(-1.0);
+    assert!((audio.peak() - 10f32.powf(-1.0 / 20.0)).abs() < 1e-5);
+    assert!((gain - 0.891 / 0.4).abs() < 0.01);
+
+    let silence = Flac::new(44_100, 16, vec![vec![0; 100]]).build();
+    let mut audio = Decoder::decode_all(silence.into_boxed_slice()).unwrap();
+    assert_eq!(audio.normalize_peak(0.0), 1.0);
+    assert_eq!(audio.peak(), 0.0);
+}
+
+#[test]
+fn fades_by_absolute_position() {
+    let pulled = |curve| {
+        let mut decoder = decoder(vec![16384; 1000]);
+        decoder.set_fade(100, 200, curve);
+        let mut pulled = Vec::new();
+        while decoder.pull(37) > 0 {
+            pulled.extend_from_slice(decoder.channel(0).unwrap());
+        }
+        pulled
+    };
+
+    let linear = pulled(FadeCurve::Linear);
+    assert_eq!(linear.len(), 1000);
+    assert_eq!(linear[0], 0.0);
+    assert!((linear[50] - 0.25).abs() < 1e-6);
+    assert_eq!(linear[100], 0.5);
+    assert_eq!(linear[799], 0.5);
+    assert!((linear[899] - 0.25).abs() < 1e-6);
+    assert_eq!(linear[999], 0.0);
+
+    let equal_power = pulled(FadeCurve::EqualPower);
+    assert!((equal_power[25] - 0.25).abs() < 1e-6);
+    assert_eq!(equal_power[100], 0.5);
+}
+
+#[test]
+fn dc_filter_removes_a_constant_offset() {
+    let samples: Vec<i32> = sine(5000, 100.0, 0.25, 16).iter().map(|s| s + 8192).collect();
+    let data = Flac::new(44_100, 16, vec![samples]).block_size(1024).build();
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.set_dc_filter(true);
+    decoder.push(&[]).unwrap();
+
+    decoder.pull(5000);
+    let filtered = decoder.channel(0).unwrap();
+    let mean = |samples: &[f32]| samples.iter().sum::<f32>() / samples.len() as f32;
+    assert!(mean(&filtered[..100]) > 0.1);
+    assert!(mean(&filtered[3000..]).abs() < 0.005);
+}
+
+#[test]
+fn crossfades_at_equal_power() {
+    let mut a = decoder(vec![16384; 2000]);
+    let mut b = decoder(vec![-8192; 600]);
+    let (mut left, mut right) = (vec![0.0; 1000], vec![0.0; 1000]);
+
+    assert_eq!(crossfade(&mut a, &mut b, 1000, &mut left, &mut right), 1000);
+    assert_eq!(left[0], 0.5);
+    let half = std::f32::consts::FRAC_1_SQRT_2;
+    assert!((left[500] - (0.5 * half - 0.25 * half)).abs() < 1e-6, "{}", left[500]);
+    assert_eq!(left, right);
+    // `b` ran out, leaving only the fading tail of `a`.
+    assert!((left[800] - 0.5 * 0.2f32.sqrt()).abs() < 1e-6, "{}", left[800]);
+    assert_eq!(a.available_samples(), 1000);
+    assert_eq!(b.available_samples(), 0);
+}
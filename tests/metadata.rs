@@ -0,0 +1,251 @@
+mod common;
+
+use common::{application, cuesheet, picture, sine, vorbis_comment, Flac, APPLICATION, CUESHEET, PADDING, PICTURE, VORBIS_COMMENT};
+use flac_decoder::{Application, BlockHeader, CueIndex, DecodeError, Decoder};
+
+fn tagged(comments: &[&str]) -> Decoder {
+    let data = Flac::new(44_100, 16, vec![sine(100, 50.0, 0.5, 16)])
+        .metadata(VORBIS_COMMENT, vorbis_comment("reference libFLAC 1.3.2 20170101", comments))
+        .build();
+    Decoder::new(data.into_boxed_slice()).unwrap()
+}
+
+#[test]
+fn reads_vorbis_comments() {
+    let decoder = tagged(&["title=Song", "Artist=Someone", "ARTIST=Someone Else"]);
+
+    assert_eq!(decoder.get_tag("TITLE").as_deref(), Some("Song"));
+    assert_eq!(decoder.get_tag("artist").as_deref(), Some("Someone"));
+    assert_eq!(decoder.get_tag("ALBUM"), None);
+    assert_eq!(decoder.tags(), &[
+        ("TITLE".to_string(), "Song".to_string()),
+        ("ARTIST".to_string(), "Someone".to_string()),
+        ("ARTIST".to_string(), "Someone Else".to_string()),
+    ]);
+}
+
+#[test]
+fn returns_every_value_of_a_repeated_tag() {
+    let decoder = tagged(&["COMPOSER=Bach", "PERFORMER=One", "composer=Handel", "Performer=Two", "PERFORMER=Three"]);
+
+    assert_eq!(decoder.get_tag_all("performer"), vec!["One", "Two", "Three"]);
+    assert_eq!(decoder.get_tag_all("COMPOSER"), vec!["Bach", "Handel"]);
+    assert!(decoder.get_tag_all("CONDUCTOR").is_empty());
+}
+
+#[test]
+fn reads_common_tags() {
+    let decoder = tagged(&[
+        "TITLE=Song",
+        "ARTIST=Someone",
+        "album=Record",
+        "TRACKNUMBER=5/12",
+        "DATE=2021-04-01",
+        "GENRE=Jazz",
+        "GENRE=Blues",
+    ]);
+    assert_eq!(decoder.title().as_deref(), Some("Song"));
+    assert_eq!(decoder.artist().as_deref(), Some("Someone"));
+    assert_eq!(decoder.album().as_deref(), Some("Record"));
+    assert_eq!(decoder.track_number(), Some(5));
+    assert_eq!(decoder.date().as_deref(), Some("2021-04-01"));
+    assert_eq!(decoder.genre().as_deref(), Some("Jazz"));
+
+    assert_eq!(tagged(&["TRACKNUMBER= 7 "]).track_number(), Some(7));
+    assert_eq!(tagged(&["TRACKNUMBER=B2"]).track_number(), None);
+    assert_eq!(tagged(&[]).title(), None);
+}
+
+#[test]
+fn reads_the_vendor_string() {
+    let decoder = tagged(&["TITLE=Song"]);
+    assert!(decoder.vendor().unwrap().starts_with("reference libFLAC"));
+
+    let data = Flac::new(44_100, 16, vec![sine(100, 50.0, 0.5, 16)]).build();
+    assert_eq!(Decoder::new(data.into_boxed_slice()).unwrap().vendor(), None);
+}
+
+#[test]
+fn reads_embedded_pictures() {
+    let jpeg = [0xff, 0xd8, 0xff, 0xe0, 0x00, 0x10, b'J', b'F', b'I', b'F', 0x00, 0xff, 0xd9];
+    let data = Flac::new(44_100, 16, vec![sine(100, 50.0, 0.5, 16)])
+        .metadata(PICTURE, picture(4, "image/png", "back", 1, 1, &[0x89, b'P', b'N', b'G']))
+        .metadata(PICTURE, picture(3, "image/jpeg", "cover", 600, 400, &jpeg))
+        .build();
+    let decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+
+    assert_eq!(decoder.pictures().len(), 2);
+    let cover = decoder.front_cover().unwrap();
+    assert_eq!(cover.mime_type, "image/jpeg");
+    assert_eq!(cover.description, "cover");
+    assert_eq!((cover.width, cover.height), (600, 400));
+    assert_eq!(cover.data, jpeg);
+}
+
+#[test]
+fn reads_application_blocks() {
+    let data = Flac::new(44_100, 16, vec![sine(100, 50.0, 0.5, 16)])
+        .metadata(APPLICATION, application(b"test", &[1, 2, 3, 4, 5]))
+        .metadata(APPLICATION, application(b"riff", &[]))
+        .build();
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+
+    assert_eq!(decoder.application_blocks(), &[
+        Application { id: *b"test", data: vec![1, 2, 3, 4, 5] },
+        Application { id: *b"riff", data: vec![] },
+    ]);
+    assert_eq!(decoder.push(&[]).unwrap(), 100);
+}
+
+#[test]
+fn reads_md5_signature() {
+    let md5 = *b"0123456789abcdef";
+    let signed = Flac::new(44_100, 16, vec![sine(100, 50.0, 0.5, 16)]).md5(md5).build();
+    let unsigned = Flac::new(44_100, 16, vec![sine(100, 50.0, 0.5, 16)]).build();
+
+    assert_eq!(Decoder::new(signed.into_boxed_slice()).unwrap().md5sum(), Some(&md5));
+    assert_eq!(Decoder::new(unsigned.into_boxed_slice()).unwrap().md5sum(), None);
+}
+
+#[test]
+fn reports_stream_length() {
+    let data = Flac::new(48_000, 16, vec![sine(72_000, 50.0, 0.5, 16)]).build();
+    let decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert_eq!(decoder.total_samples(), 72_000);
+    assert_eq!(decoder.duration_seconds(), 1.5);
+
+    let data = Flac::new(48_000, 16, vec![sine(1000, 50.0, 0.5, 16)]).total_samples(0).build();
+    let decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert_eq!(decoder.total_samples(), 0);
+    assert_eq!(decoder.duration_seconds(), 0.0);
+}
+
+#[test]
+fn computes_an_unknown_stream_length() {
+    let samples = sine(72_345, 50.0, 0.5, 16);
+    let data = Flac::new(48_000, 16, vec![samples.clone()]).block_size(1000).total_samples(0).build();
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert_eq!(decoder.compute_duration().unwrap(), 72_345);
+    assert_eq!(decoder.total_samples(), 72_345);
+
+    let data = Flac::new(48_000, 16, vec![samples]).variable_block_sizes(&[1000, 300]).total_samples(0).build();
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert_eq!(decoder.compute_duration().unwrap(), 72_345);
+
+    let flac = Flac::new(48_000, 16, vec![sine(1000, 50.0, 0.5, 16)]).total_samples(0);
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    assert_eq!(decoder.compute_duration().err().map(|err| err.code()), Some("unknown_duration"));
+}
+
+#[test]
+fn reports_block_and_frame_sizes() {
+    let flac = Flac::new(44_100, 16, vec![sine(10_000, 50.0, 0.5, 16)]).block_size(1024);
+    let frames = flac.frames();
+    let decoder = Decoder::new(flac.build().into_boxed_slice()).unwrap();
+
+    assert_eq!((decoder.min_block_size(), decoder.max_block_size()), (1024, 1024));
+    assert_eq!(decoder.min_frame_size(), frames.iter().map(|f| f.len() as u32).min());
+    assert_eq!(decoder.max_frame_size(), frames.iter().map(|f| f.len() as u32).max());
+}
+
+#[test]
+fn skips_leading_id3v2_tag() {
+    let samples = sine(1000, 50.0, 0.5, 16);
+    let mut data = b"ID3\x03\x00\x00\x00\x00\x0f\x76".to_vec();
+    data.resize(10 + 2038, 0);
+    data.extend(Flac::new(44_100, 16, vec![samples.clone()]).build());
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert_eq!(decoder.push(&[]).unwrap(), 1000);
+    assert_eq!(decoder.sample(0, 10), Some(samples[10] as f32 / 32768.0));
+}
+
+#[test]
+fn reads_cue_sheet_tracks() {
+    let index: &[(u64, u8)] = &[(0, 1)];
+    let indices: &[(u64, u8)] = &[(0, 0), (588, 1)];
+    let data = Flac::new(44_100, 16, vec![sine(10_000, 50.0, 0.5, 16)])
+        .metadata(CUESHEET, cuesheet(88_200, true, &[
+            (0, 1, "USRC17607839", index),
+            (4410, 2, "", indices),
+            (10_000, 170, "", &[]),
+        ]))
+        .build();
+    let decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+
+    let cue_sheet = decoder.cue_sheet().unwrap();
+    assert_eq!(cue_sheet.lead_in, 88_200);
+    assert!(cue_sheet.is_cd);
+    assert_eq!(cue_sheet.tracks.len(), 2);
+    assert_eq!(cue_sheet.tracks[0].offset, 0);
+    assert_eq!(cue_sheet.tracks[0].isrc, "USRC17607839");
+    assert_eq!(cue_sheet.tracks[1].offset, 4410);
+    assert_eq!(cue_sheet.tracks[1].indices[1], CueIndex { number: 1, offset: 588 });
+    assert_eq!(decoder.cue_lead_out(), Some(10_000));
+}
+
+#[test]
+fn calls_the_metadata_callback_once() {
+    let data = Flac::new(44_100, 16, vec![sine(100, 50.0, 0.5, 16)])
+        .metadata(VORBIS_COMMENT, vorbis_comment("reference libFLAC 1.3.2 20170101", &["TITLE=Tune"]))
+        .build();
+
+    let mut calls = 0;
+    let decoder = Decoder::with_metadata_callback(data.clone().into_boxed_slice(), |decoder| {
+        calls += 1;
+        assert_eq!(decoder.tags(), &[("TITLE".to_string(), "Tune".to_string())]);
+        assert_eq!(decoder.available_samples(), 0);
+        Ok(())
+    });
+    assert!(decoder.is_ok());
+    assert_eq!(calls, 1);
+
+    let failed = Decoder::with_metadata_callback(data.into_boxed_slice(), |_| Err(DecodeError::Callback("no".into())));
+    assert_eq!(failed.err().map(|err| err.code()), Some("callback"));
+}
+
+#[test]
+fn lists_every_metadata_block() {
+    let data = Flac::new(44_100, 16, vec![sine(100, 50.0, 0.5, 16)])
+        .metadata(VORBIS_COMMENT, vorbis_comment("reference libFLAC 1.3.2 20170101", &[]))
+        .metadata(PADDING, vec![0; 1000])
+        .build();
+    let decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+
+    assert_eq!(decoder.block_headers(), &[
+        BlockHeader { block_type: 0, length: 34, is_last: false },
+        BlockHeader { block_type: VORBIS_COMMENT, length: 40, is_last: false },
+        BlockHeader { block_type: PADDING, length: 1000, is_last: true },
+    ]);
+}
+
+#[test]
+fn tells_subset_streams_apart() {
+    let subset = |rate, bits, block_size| {
+        let data = Flac::new(rate, bits, vec![sine(20_000, 50.0, 0.5, bits)]).block_size(block_size).build();
+        Decoder::new(data.into_boxed_slice()).unwrap().is_subset()
+    };
+    assert!(subset(44_100, 16, 4096));
+    assert!(subset(96_000, 24, 8192));
+    assert!(!subset(44_100, 16, 8192));
+    // A 17-bit frame header has to defer its bit depth to STREAMINFO.
+    assert!(!subset(44_100, 17, 4096));
+
+    // Frame headers can code 32 bits, but the Subset stops at 24.
+    let info = Flac::new(44_100, 32, vec![sine(20_000, 50.0, 0.5, 32)]).header();
+    assert!(!Decoder::new(info.into_boxed_slice()).unwrap().is_subset());
+}
+
+#[test]
+fn checks_the_first_frame_for_the_subset_after_decoding_it() {
+    let samples = sine(20_000, 50.0, 0.5, 16);
+    // STREAMINFO claims blocks within the limit, the frames are larger.
+    let mut data = Flac::new(44_100, 16, vec![samples.clone()]).block_size(4096).header();
+    data.extend(Flac::new(44_100, 16, vec![samples]).block_size(8192).frames().concat());
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    assert!(!decoder.is_subset());
+    assert_eq!(decoder.push(&[]).unwrap(), 20_000);
+    assert_eq!(decoder.buffered_input_len(), 0);
+    assert!(!decoder.is_subset());
+}
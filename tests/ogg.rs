@@ -0,0 +1,27 @@
+mod common;
+
+use common::{ramp, vorbis_comment, Flac, VORBIS_COMMENT};
+use flac_decoder::Decoder;
+
+#[test]
+fn decodes_ogg_flac() {
+    let left = ramp(20_000, 24);
+    let right: Vec<i32> = left.iter().map(|s| s / 3).collect();
+    let flac = Flac::new(96_000, 24, vec![left.clone(), right])
+        .block_size(4096)
+        .metadata(VORBIS_COMMENT, vorbis_comment("test", &["TITLE=Ogg"]));
+    let data = common::ogg(&flac);
+    let (head, rest) = data.split_at(200);
+
+    let mut decoder = Decoder::new(head.to_vec().into_boxed_slice()).unwrap();
+    assert_eq!(decoder.sample_rate(), 96_000);
+    assert_eq!(decoder.bit_depth(), 24);
+    assert_eq!(decoder.get_tag("title").as_deref(), Some("Ogg"));
+
+    let mut total = 0;
+    for chunk in rest.chunks(1000) {
+        total += decoder.push(chunk).unwrap();
+    }
+    assert_eq!(total, 20_000);
+    assert_eq!(decoder.sample(0, 19_999), Some(left[19_999] as f32 / 8_388_608.0));
+}
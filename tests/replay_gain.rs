@@ -0,0 +1,75 @@
+mod common;
+
+use common::{sine, vorbis_comment, Flac, VORBIS_COMMENT};
+use flac_decoder::{Decoder, ReplayGain, ReplayGainMode};
+
+fn decoder(comments: &[&str]) -> Decoder {
+    let data = Flac::new(44_100, 16, vec![sine(1000, 100.0, 0.25, 16)])
+        .metadata(VORBIS_COMMENT, vorbis_comment("test", comments))
+        .build();
+    Decoder::new(data.into_boxed_slice()).unwrap()
+}
+
+fn peak(decoder: &mut Decoder) -> f32 {
+    decoder.push(&[]).unwrap();
+    decoder.pull(1000);
+    decoder.channel(0).unwrap().iter().fold(0.0, |peak, s| peak.max(s.abs()))
+}
+
+#[test]
+fn parses_replay_gain_tags() {
+    let decoder = decoder(&[
+        "REPLAYGAIN_TRACK_GAIN=-6.48 dB",
+        "REPLAYGAIN_TRACK_PEAK=0.988553",
+        "replaygain_album_gain=+1.5 dB",
+    ]);
+
+    assert_eq!(decoder.replay_gain_tags(), &ReplayGain {
+        track_gain: Some(-6.48),
+        track_peak: Some(0.988553),
+        album_gain: Some(1.5),
+        album_peak: None,
+    });
+}
+
+#[test]
+fn queries_each_value_separately() {
+    let untagged = decoder(&["TITLE=Untagged"]);
+    assert_eq!(untagged.replay_gain_track(), None);
+    assert_eq!(untagged.replay_gain_album(), None);
+    assert_eq!(untagged.replay_gain_track_peak(), None);
+    assert_eq!(untagged.replay_gain_album_peak(), None);
+
+    let tagged = decoder(&[
+        "REPLAYGAIN_TRACK_GAIN=  \u{2212}7.89  dB ",
+        "REPLAYGAIN_TRACK_PEAK=0,5",
+        "REPLAYGAIN_ALBUM_GAIN=loud",
+        "REPLAYGAIN_ALBUM_PEAK=1.2",
+    ]);
+    assert_eq!(tagged.replay_gain_track(), Some(-7.89));
+    assert_eq!(tagged.replay_gain_track_peak(), Some(0.5));
+    assert_eq!(tagged.replay_gain_album(), None);
+    assert_eq!(tagged.replay_gain_album_peak(), Some(1.2));
+}
+
+#[test]
+fn track_gain_scales_output() {
+    let unity = peak(&mut decoder(&[]));
+
+    let mut decoder = decoder(&["REPLAYGAIN_TRACK_GAIN=+6.00 dB", "REPLAYGAIN_TRACK_PEAK=0.25"]);
+    decoder.set_replay_gain_mode(ReplayGainMode::Track);
+    let gained = peak(&mut decoder);
+
+    assert!((gained / unity - 1.995).abs() < 0.01, "{} vs {}", gained, unity);
+}
+
+#[test]
+fn peak_limits_the_gain() {
+    let unity = peak(&mut decoder(&[]));
+
+    let mut decoder = decoder(&["REPLAYGAIN_TRACK_GAIN=+12.00 dB", "REPLAYGAIN_TRACK_PEAK=0.5"]);
+    decoder.set_replay_gain_mode(ReplayGainMode::Album);
+    let gained = peak(&mut decoder);
+
+    assert!((gained / unity - 2.0).abs() < 0.001, "{} vs {}", gained, unity);
+}
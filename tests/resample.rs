@@ -0,0 +1,39 @@
+mod common;
+
+use common::{sine, Flac};
+use flac_decoder::Decoder;
+
+#[test]
+fn resamples_to_the_target_rate() {
+    let flac = Flac::new(44_100, 16, vec![sine(44_100, 441.0, 0.5, 16)]).block_size(4096);
+
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    decoder.set_target_sample_rate(Some(48_000));
+    assert_eq!(decoder.sample_rate(), 48_000);
+
+    let mut decoded = 0;
+    for chunk in flac.frames().concat().chunks(3000) {
+        decoded += decoder.push(chunk).unwrap();
+    }
+    assert!((decoded as i64 - 48_000).abs() <= 1, "{} frames", decoded);
+    assert_eq!(decoder.available_samples(), decoded);
+    assert_eq!(decoder.duration_seconds(), 1.0);
+
+    // A 100 Hz tone at 48 kHz, continuous across frame and push boundaries.
+    decoder.pull(decoded);
+    for (i, &s) in decoder.channel(0).unwrap().iter().enumerate().skip(1) {
+        let expected = (i as f64 * std::f64::consts::TAU / 480.0).sin() * 0.5;
+        assert!((s as f64 - expected).abs() < 0.002, "sample {}: {} vs {}", i, s, expected);
+    }
+}
+
+#[test]
+fn matching_target_rate_is_a_no_op() {
+    let samples = sine(1000, 100.0, 0.5, 16);
+    let data = Flac::new(48_000, 16, vec![samples.clone()]).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.set_target_sample_rate(Some(48_000));
+    assert_eq!(decoder.push(&[]).unwrap(), 1000);
+    assert_eq!(decoder.sample(0, 10), Some(samples[10] as f32 / 32768.0));
+}
@@ -0,0 +1,210 @@
+mod common;
+
+use common::{ramp, Flac};
+use flac_decoder::{Decoder, SeekPoint};
+
+fn decoder(samples: &[i32]) -> Decoder {
+    let data = Flac::new(44_100, 16, vec![samples.to_vec()]).block_size(1000).with_seektable(3).build();
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.push(&[]).unwrap();
+    decoder
+}
+
+#[test]
+fn seeks_to_exact_sample() {
+    let samples = ramp(10_000, 16);
+    let mut decoder = decoder(&samples);
+
+    assert_eq!(decoder.seek(4500).unwrap(), 4500);
+    assert_eq!(decoder.available_samples(), 5500);
+    assert_eq!(decoder.sample(0, 0), Some(samples[4500] as f32 / 32768.0));
+}
+
+#[test]
+fn seeks_backwards_after_decoding_everything() {
+    let samples = ramp(10_000, 16);
+    let mut decoder = decoder(&samples);
+    decoder.pull(10_000);
+
+    assert_eq!(decoder.seek(123).unwrap(), 123);
+    assert_eq!(decoder.sample(0, 0), Some(samples[123] as f32 / 32768.0));
+    assert_eq!(decoder.seek(9000).unwrap(), 9000);
+    assert_eq!(decoder.available_samples(), 1000);
+}
+
+#[test]
+fn seek_past_the_end_stops_at_the_last_sample() {
+    let mut decoder = decoder(&ramp(10_000, 16));
+
+    assert_eq!(decoder.seek(20_000).unwrap(), 10_000);
+    assert_eq!(decoder.available_samples(), 0);
+}
+
+#[test]
+fn tells_whether_the_stream_is_seekable() {
+    let samples = ramp(10_000, 16);
+    assert!(decoder(&samples).is_seekable());
+
+    let flac = Flac::new(44_100, 16, vec![samples.clone()]).block_size(1000);
+    let mut decoder = Decoder::new(flac.build().into_boxed_slice()).unwrap();
+    assert!(decoder.is_seekable());
+    assert_eq!(decoder.seek(4500).unwrap(), 4500);
+    assert_eq!(decoder.sample(0, 0), Some(samples[4500] as f32 / 32768.0));
+
+    let frames = flac.frames();
+    let mut partial = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    partial.push(&frames[..5].concat()).unwrap();
+    assert!(!partial.is_seekable());
+    assert_eq!(partial.seek(100).err().map(|e| e.code()), Some("no_seek_table"));
+}
+
+#[test]
+fn bisects_a_buffered_stream_without_a_seek_table() {
+    let samples = common::sine(1_000_000, 441.0, 0.5, 16);
+    let data = Flac::new(44_100, 16, vec![samples.clone()]).block_size(1000).build();
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+
+    assert_eq!(decoder.seek(612_345).unwrap(), 612_345);
+    assert_eq!(decoder.sample(0, 0), Some(samples[612_345] as f32 / 32768.0));
+    // Decoding started at most one frame before the target, not at the start.
+    assert!(decoder.decode_stats().frames <= 389, "{:?}", decoder.decode_stats());
+}
+
+#[test]
+fn exposes_seek_table_without_placeholders() {
+    let flac = Flac::new(44_100, 16, vec![ramp(10_000, 16)]).block_size(1000);
+    let offsets = flac.frame_offsets();
+    let points = [(0, 0, 1000), (5000, offsets[5], 1000), (u64::MAX, 0, 0), (u64::MAX, 0, 0)];
+    let data = flac.metadata(common::SEEKTABLE, common::seektable(&points)).build();
+
+    let decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    let table = decoder.seek_table();
+    assert_eq!(table.len(), 2);
+    assert_eq!(table[0], SeekPoint { sample: 0, offset: 0, samples: 1000 });
+    assert_eq!(table[1], SeekPoint { sample: 5000, offset: offsets[5], samples: 1000 });
+}
+
+#[test]
+fn memory_usage_drops_after_free_input() {
+    let samples = ramp(10_000, 16);
+    let mut decoder = decoder(&samples);
+    let decoded = decoder.memory_usage();
+    assert!(decoded > 10_000 * 4, "{}", decoded);
+
+    decoder.free_input();
+    assert!(decoder.memory_usage() < decoded);
+}
+
+#[test]
+fn memory_usage_drops_after_shrinking_drained_output() {
+    let mut decoder = decoder(&ramp(10_000, 16));
+    decoder.pull(10_000);
+    decoder.pull(0);
+    let drained = decoder.memory_usage();
+
+    decoder.shrink_output_to_fit();
+    assert!(decoder.memory_usage() <= drained - 10_000 * 4, "{} vs {}", decoder.memory_usage(), drained);
+}
+
+#[test]
+fn seeks_only_within_input_kept_after_free_input() {
+    let samples = ramp(10_000, 16);
+    let flac = Flac::new(44_100, 16, vec![samples.clone()]).block_size(1000).with_seektable(3);
+    let frames = flac.frames();
+
+    let mut decoder = Decoder::new(flac.header().into_boxed_slice()).unwrap();
+    decoder.push(&frames[..5].concat()).unwrap();
+    decoder.free_input();
+    decoder.shrink_output_to_fit();
+    assert_eq!(decoder.push(&frames[5..].concat()).unwrap(), 5000);
+
+    assert_eq!(decoder.seek(7500).unwrap(), 7500);
+    assert_eq!(decoder.sample(0, 0), Some(samples[7500] as f32 / 32768.0));
+    assert_eq!(decoder.seek(100).err().map(|err| err.code()), Some("not_buffered"));
+}
+
+#[test]
+fn seeks_to_a_time_in_seconds() {
+    let samples = ramp(10_000, 16);
+    let mut decoder = decoder(&samples);
+
+    let midpoint = decoder.duration_seconds() / 2.0;
+    assert_eq!(decoder.seek_seconds(midpoint).unwrap(), midpoint);
+    assert_eq!(decoder.sample(0, 0), Some(samples[5000] as f32 / 32768.0));
+
+    assert_eq!(decoder.seek_seconds(-1.0).unwrap(), 0.0);
+    assert_eq!(decoder.seek_seconds(60.0).unwrap(), decoder.duration_seconds());
+}
+
+#[test]
+fn counts_pulled_samples_from_the_seek_position() {
+    let mut decoder = decoder(&ramp(10_000, 16));
+    for size in [100, 250, 1000] {
+        decoder.pull(size);
+    }
+    decoder.pull_interleaved(50);
+    decoder.pull_mono(10);
+    assert_eq!(decoder.pulled_samples(), 1410);
+
+    decoder.seek(4500).unwrap();
+    decoder.pull(500);
+    assert_eq!(decoder.pulled_samples(), 5000);
+    assert!((decoder.played_seconds() - 5000.0 / 44_100.0).abs() < 1e-12);
+
+    decoder.reset();
+    assert_eq!(decoder.pulled_samples(), 0);
+}
+
+#[test]
+fn decodes_a_byte_range_from_the_middle_of_a_file() {
+    let samples = ramp(10_000, 16);
+    let flac = Flac::new(44_100, 16, vec![samples.clone()]).block_size(1000);
+    let frames = flac.frames();
+    // The range starts in the middle of frame 3 and ends in the middle of frame 8.
+    let mut range = frames[3][frames[3].len() / 2..].to_vec();
+    range.extend(frames[4..8].concat());
+    range.extend(&frames[8][..frames[8].len() / 2]);
+
+    let stream_info = flac.streaminfo_bytes().into_boxed_slice();
+    let mut decoder = Decoder::new_headerless_at(stream_info, range.clone().into_boxed_slice(), 0).unwrap();
+    assert_eq!(decoder.start_sample(), 4000);
+    assert_eq!(decoder.push(&[]).unwrap(), 4000);
+    assert_eq!(decoder.sample_position(), 8000);
+    assert_eq!(decoder.sample(0, 0), Some(samples[4000] as f32 / 32768.0));
+    assert_eq!(decoder.sample(0, 3999), Some(samples[7999] as f32 / 32768.0));
+
+    let head = Decoder::new_headerless_at(flac.header().into_boxed_slice(), range.into_boxed_slice(), 3000).unwrap();
+    assert_eq!(head.start_sample(), 4000);
+    assert!(!head.is_seekable());
+
+    let torn = frames[3][1..].to_vec().into_boxed_slice();
+    let err = Decoder::new_headerless_at(flac.streaminfo_bytes().into_boxed_slice(), torn, 0).err();
+    assert_eq!(err.map(|e| e.code()), Some("no_frame"));
+}
+
+#[test]
+fn seeks_across_a_gapless_join() {
+    let (first, second) = (ramp(3000, 16), common::sine(2500, 100.0, 0.5, 16));
+    let mut data = Flac::new(44_100, 16, vec![first.clone()]).block_size(1000).build();
+    data.extend(Flac::new(44_100, 16, vec![second.clone()]).block_size(1024).build());
+
+    let mut decoder = Decoder::new(data.clone().into_boxed_slice()).unwrap();
+    decoder.set_gapless(true);
+    assert_eq!(decoder.push(&[]).unwrap(), 5500);
+    // The second stream numbers its frames from 0 in blocks of 1024.
+    assert_eq!(decoder.last_frame_start_sample(), 3000 + 2048);
+    assert!(decoder.is_seekable());
+
+    assert_eq!(decoder.seek(4100).unwrap(), 4100);
+    assert_eq!(decoder.sample(0, 0), Some(second[1100] as f32 / 32768.0));
+    assert_eq!(decoder.pull(10_000), 1400);
+
+    assert_eq!(decoder.seek(2500).unwrap(), 2500);
+    assert_eq!(decoder.sample(0, 0), Some(first[2500] as f32 / 32768.0));
+    assert_eq!(decoder.sample(0, 500), Some(second[0] as f32 / 32768.0));
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.set_gapless(true);
+    let starts: Vec<u64> = decoder.frames().map(|frame| frame.start_sample()).collect();
+    assert_eq!(starts, [0, 1000, 2000, 3000, 4024, 5048]);
+}
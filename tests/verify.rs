@@ -0,0 +1,38 @@
+mod common;
+
+use common::{ramp, Flac};
+use flac_decoder::Decoder;
+
+// MD5 of `ramp(5000, 16)` interleaved with its inverse as 16-bit little-endian PCM.
+const MD5: [u8; 16] = [105, 40, 98, 124, 0, 18, 80, 225, 66, 82, 13, 5, 221, 23, 114, 77];
+
+fn stereo(left: Vec<i32>) -> Flac {
+    let right = left.iter().map(|s| -s - 1).collect();
+    Flac::new(44_100, 16, vec![left, right]).block_size(1152).md5(MD5)
+}
+
+#[test]
+fn verifies_intact_audio() {
+    let flac = stereo(ramp(5000, 16));
+    let header_len = flac.header().len();
+    let data = flac.build();
+
+    let mut decoder = Decoder::new(data[..header_len].to_vec().into_boxed_slice()).unwrap();
+    decoder.set_verify(true);
+    for chunk in data[header_len..].chunks(777) {
+        decoder.push(chunk).unwrap();
+    }
+    assert!(decoder.finish_verify().unwrap());
+}
+
+#[test]
+fn detects_altered_audio() {
+    let mut samples = ramp(5000, 16);
+    samples[2500] ^= 1;
+    let data = stereo(samples).build();
+
+    let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+    decoder.set_verify(true);
+    decoder.push(&[]).unwrap();
+    assert!(!decoder.finish_verify().unwrap());
+}
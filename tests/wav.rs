@@ -0,0 +1,86 @@
+mod common;
+
+use common::{ramp, Flac};
+use flac_decoder::Decoder;
+
+fn u16_at(wav: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([wav[pos], wav[pos + 1]])
+}
+
+fn u32_at(wav: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([wav[pos], wav[pos + 1], wav[pos + 2], wav[pos + 3]])
+}
+
+fn wav(bits: u32, channels: Vec<Vec<i32>>) -> Vec<u8> {
+    let data = Flac::new(48_000, bits, channels).build();
+    Decoder::decode_all(data.into_boxed_slice()).unwrap().wav_bytes().unwrap()
+}
+
+#[test]
+fn writes_a_canonical_header() {
+    let wav = wav(16, vec![ramp(1000, 16), ramp(1000, 16)]);
+
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(u32_at(&wav, 4) as usize, wav.len() - 8);
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(u32_at(&wav, 16), 16);
+    assert_eq!(u16_at(&wav, 20), 1);
+    assert_eq!(u16_at(&wav, 22), 2);
+    assert_eq!(u32_at(&wav, 24), 48_000);
+    assert_eq!(u32_at(&wav, 28), 48_000 * 4);
+    assert_eq!(u16_at(&wav, 32), 4);
+    assert_eq!(u16_at(&wav, 34), 16);
+    assert_eq!(&wav[36..40], b"data");
+    assert_eq!(u32_at(&wav, 40), 4000);
+    assert_eq!(wav.len(), 44 + 4000);
+}
+
+#[test]
+fn names_the_speakers_of_surround_audio() {
+    // FL FR FC LFE BL BR
+    let channels: Vec<Vec<i32>> = (1..=6).map(|ch| vec![ch * 256; 10]).collect();
+    let data = Flac::new(48_000, 16, channels).build();
+    let decoder = Decoder::new(data.clone().into_boxed_slice()).unwrap();
+    assert_eq!(decoder.speakers(), &["FL", "FR", "FC", "LFE", "BL", "BR"]);
+
+    let wav = Decoder::decode_all(data.into_boxed_slice()).unwrap().wav_bytes().unwrap();
+    assert_eq!(u32_at(&wav, 16), 40);
+    assert_eq!(u16_at(&wav, 20), 0xfffe);
+    assert_eq!(u16_at(&wav, 22), 6);
+    assert_eq!(u16_at(&wav, 38), 16);
+    assert_eq!(u32_at(&wav, 40), 0x3f);
+    assert_eq!(&wav[60..64], b"data");
+    // The centre and LFE stay third and fourth, where the mask puts them.
+    let first: Vec<u16> = (0..6).map(|ch| u16_at(&wav, 68 + 2 * ch)).collect();
+    assert_eq!(first, [256, 512, 768, 1024, 1280, 1536]);
+    assert_eq!(wav.len(), 68 + 10 * 12);
+}
+
+#[test]
+fn writes_samples_in_their_container() {
+    let samples = vec![0, 1, -1, 8_388_607, -8_388_608];
+    let wav24 = wav(24, vec![samples]);
+    assert_eq!(u16_at(&wav24, 34), 24);
+    assert_eq!(&wav24[44..], &[0, 0, 0, 1, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f, 0, 0, 0x80]);
+
+    // 12-bit samples are left-justified in 16 bits.
+    let wav12 = wav(12, vec![vec![1, -2048]]);
+    assert_eq!(u16_at(&wav12, 34), 16);
+    assert_eq!(&wav12[44..], &[0x10, 0, 0, 0x80]);
+
+    let wav8 = wav(8, vec![vec![0, 127, -128]]);
+    assert_eq!(&wav8[44..], &[128, 255, 0]);
+}
+
+#[test]
+fn exports_raw_pcm_without_a_header() {
+    let data = Flac::new(48_000, 16, vec![ramp(1000, 16), ramp(1000, 16)]).build();
+    let audio = Decoder::decode_all(data.into_boxed_slice()).unwrap();
+    let pcm = audio.pcm_bytes();
+    assert_eq!(pcm.len(), audio.frames() * 2 * 2);
+    assert_eq!(pcm, &audio.wav_bytes().unwrap()[44..]);
+
+    let data = Flac::new(48_000, 24, vec![vec![0, 1, -1, 8_388_607, -8_388_608]]).build();
+    let pcm = Decoder::decode_all(data.into_boxed_slice()).unwrap().pcm_bytes();
+    assert_eq!(pcm, [0, 0, 0, 1, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f, 0, 0, 0x80]);
+}
@@ -11,3 +11,71 @@ wasm_bindgen_test_configure!(run_in_browser);
 fn pass() {
     assert_eq!(1 + 1, 2);
 }
+
+mod common;
+
+#[path = "../src/convert.rs"]
+mod convert;
+
+use std::collections::VecDeque;
+
+use common::{ramp, Flac};
+use flac_decoder::Decoder;
+
+// Runs the simd128 conversion when built with `RUSTFLAGS="-C target-feature=+simd128"`.
+#[wasm_bindgen_test]
+fn converts_samples_like_the_scalar_path() {
+    for bits in [8, 16, 24] {
+        let samples = ramp(4099, bits);
+        let data = Flac::new(44_100, bits, vec![samples.clone()]).build();
+        let mut decoder = Decoder::new(data.into_boxed_slice()).unwrap();
+        decoder.push(&[]).unwrap();
+
+        let full_scale = (1u64 << (bits - 1)) as f32;
+        for (i, &sample) in samples.iter().enumerate() {
+            assert_eq!(decoder.sample(0, i), Some(sample as f32 / full_scale));
+        }
+    }
+}
+
+// Without simd128 this compares the scalar path with itself; CI runs it with the feature on.
+#[wasm_bindgen_test]
+fn simd_conversion_is_bit_identical_to_scalar() {
+    for bits in [4, 8, 12, 16, 20, 24, 32] {
+        // 4099 leaves a remainder for the scalar tail after the four-wide chunks.
+        let mut samples = ramp(4099, bits);
+        samples.extend(&[i32::MIN >> (32 - bits), i32::MAX >> (32 - bits), 0, -1, 1]);
+        for &scale in &[1.0, 0.5, 1.7] {
+            let (mut simd, mut scalar) = (VecDeque::new(), VecDeque::new());
+            let (mut simd_peak, mut scalar_peak) = (0.25, 0.25);
+            convert::extend_f32(&mut simd, &samples, bits, scale, &mut simd_peak);
+            convert::extend_f32_scalar(&mut scalar, &samples, bits, scale, &mut scalar_peak);
+
+            assert_eq!(simd.len(), samples.len());
+            for (i, (a, b)) in simd.iter().zip(&scalar).enumerate() {
+                assert_eq!(a.to_bits(), b.to_bits(), "{} bits, scale {}, sample {}", bits, scale, i);
+            }
+            assert_eq!(simd_peak.to_bits(), scalar_peak.to_bits());
+        }
+    }
+}
+
+#[wasm_bindgen_test]
+fn returns_stream_info_as_one_object() {
+    use js_sys::Reflect;
+    use wasm_bindgen::JsValue;
+
+    let data = Flac::new(48_000, 24, vec![ramp(5000, 24), ramp(5000, 24)]).block_size(1024).build();
+    let info = Decoder::new(data.into_boxed_slice()).unwrap().stream_info();
+    let get = |key: &str| Reflect::get(&info, &JsValue::from_str(key)).unwrap();
+
+    assert_eq!(get("sample_rate").as_f64(), Some(48_000.0));
+    assert_eq!(get("channels").as_f64(), Some(2.0));
+    assert_eq!(get("bits_per_sample").as_f64(), Some(24.0));
+    assert_eq!(get("total_samples").as_f64(), Some(5000.0));
+    assert_eq!(get("min_block_size").as_f64(), Some(1024.0));
+    assert_eq!(get("max_block_size").as_f64(), Some(1024.0));
+    assert!(get("min_frame_size").as_f64().is_some());
+    assert!(get("max_frame_size").as_f64().is_some());
+    assert!(get("md5").is_undefined());
+}